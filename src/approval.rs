@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use indexmap::IndexMap;
+use log::warn;
+use serde::{Serialize, Deserialize};
+use tokio::sync::watch;
+use crate::model::render::{DiffFormat, DiffRenderer};
+use crate::model::sedaroml::{Model, ModelDiff};
+
+/// Whether the changes computed by a translation round must be approved before they are written to the Nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApprovalMode {
+  /// Write each round's changes as soon as they are computed
+  #[default]
+  Disabled,
+  /// Hold each round's changes until `Exchange::approve` or `Exchange::reject` is called
+  Manual,
+  /// Prompt for approval of each round's changes on stdin
+  Interactive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+  Approve,
+  Reject,
+}
+
+/// Changes computed by a translation round that are held until they are approved or rejected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Changeset {
+  /// Sequence number the round will have once it is complete
+  pub round: u64,
+  /// Identifier of the Node whose change started the round
+  pub trigger: String,
+  /// Diff to apply to each Node's representation, in the order they will be written
+  pub diffs: IndexMap<String, ModelDiff>,
+}
+
+/// Hands pending changesets from the translation thread to whoever approves or rejects them.
+#[derive(Clone)]
+pub struct ApprovalGate {
+  pending: Arc<watch::Sender<Option<Changeset>>>,
+  tx: mpsc::Sender<Decision>,
+  rx: Arc<Mutex<mpsc::Receiver<Decision>>>,
+}
+
+impl ApprovalGate {
+  pub fn new() -> ApprovalGate {
+    let (tx, rx) = mpsc::channel();
+    ApprovalGate {
+      pending: Arc::new(watch::Sender::new(None)),
+      tx,
+      rx: Arc::new(Mutex::new(rx)),
+    }
+  }
+
+  pub fn pending(&self) -> Option<Changeset> {
+    self.pending.borrow().clone()
+  }
+
+  /// Blocks until a changeset is awaiting a decision and returns it
+  pub fn wait_for_pending(&self) -> Changeset {
+    futures::executor::block_on(self.wait_for_pending_async())
+  }
+
+  pub async fn wait_for_pending_async(&self) -> Changeset {
+    let mut rx = self.pending.subscribe();
+    let pending = rx.wait_for(|pending| pending.is_some()).await.expect("Approval gate closed").clone();
+    pending.unwrap()
+  }
+
+  /// Decides on the pending changeset and returns it, or returns `None` if no changeset is pending
+  pub(crate) fn decide(&self, decision: Decision) -> Option<Changeset> {
+    let changeset = self.pending.send_replace(None)?;
+    self.tx.send(decision).ok();
+    Some(changeset)
+  }
+
+  /// Publishes a changeset and blocks until it is decided on.  Returns `None` if `cancelled` returns true first.
+  pub(crate) fn request(&self, changeset: Changeset, cancelled: impl Fn() -> bool) -> Option<Decision> {
+    let rx = self.rx.lock().unwrap();
+    self.pending.send_replace(Some(changeset));
+    loop {
+      match rx.recv_timeout(Duration::from_millis(100)) {
+        Ok(decision) => return Some(decision),
+        Err(RecvTimeoutError::Timeout) if cancelled() => {
+          self.pending.send_replace(None);
+          return None;
+        },
+        Err(_) => {},
+      }
+    }
+  }
+}
+
+impl Default for ApprovalGate {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Prompts for a decision on stdin, naming blocks by the current representations `reps` of the changed Nodes
+pub(crate) fn prompt_for_approval(changeset: &Changeset, reps: &HashMap<String, Model>) -> Decision {
+  warn!("Round {} triggered by {} is awaiting approval:", changeset.round, changeset.trigger);
+  for (iden, diff) in &changeset.diffs {
+    let mut renderer = DiffRenderer::new(DiffFormat::Terminal);
+    if let Some(rep) = reps.get(iden) {
+      renderer = renderer.with_model(rep);
+    }
+    warn!("  {}:\n{}", iden, renderer.render(diff));
+  }
+  warn!("  (a) - Approve and write the changes");
+  warn!("  (R) - Reject the changes");
+  let mut buffer = String::new();
+  io::stdin().read_line(&mut buffer).expect("Failed to read line");
+  match buffer.trim().to_lowercase().as_str() {
+    "a" => Decision::Approve,
+    _ => Decision::Reject,
+  }
+}
@@ -1,4 +1,5 @@
 use std::time::Duration;
+use serde::{Serialize, Deserialize};
 use crate::model::sedaroml::ModelDiff;
 
 #[derive(Debug)]
@@ -29,10 +30,13 @@ pub enum NodeResponses {
   ConflictResolved(Duration),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConflictResolutions {
   /// Signals to keep the current representation and reconcile the source document/model
   KeepRep,
   /// Signals to update the current representation from the source document/model
   UpdateRep,
+  /// Signals to merge the changes made to the current representation and the source document/model since they last
+  /// agreed, keeping the current representation's side of any conflicting change
+  Merge,
 }
\ No newline at end of file
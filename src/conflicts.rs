@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use log::warn;
+use crate::commands::ConflictResolutions;
+use crate::exchange::ExchangeError;
+use crate::model::render::{DiffFormat, DiffRenderer};
+use crate::model::sedaroml::{read_model, write_model, Model, ModelDiff, ModelError};
+
+/// Callback deciding how to resolve a Node's conflict from its identifier and the conflicting diff.  Returning `None`
+/// leaves the conflict unresolved.
+pub type ConflictCallback = Arc<dyn Fn(&str, &ModelDiff) -> Option<ConflictResolutions> + Send + Sync>;
+
+/// How the Exchange resolves conflicts between a Node's representation and its foreign model.
+#[derive(Clone, Default)]
+pub enum ConflictPolicy {
+  /// Prompt for a resolution strategy on stdin
+  #[default]
+  Interactive,
+  /// Always keep the current representation and reconcile the foreign model
+  KeepRep,
+  /// Always update the current representation from the foreign model
+  UpdateRep,
+  /// Always merge the changes made to the current representation and the foreign model
+  Merge,
+  /// Fail on the first conflict
+  FailFast,
+  /// Use the policy listed for a Node, falling back to `default` for Nodes that aren't listed
+  PerNode { overrides: HashMap<String, ConflictPolicy>, default: Box<ConflictPolicy> },
+  /// Defer to a user-supplied callback
+  Custom(ConflictCallback),
+}
+
+impl ConflictPolicy {
+  /// Decides how to resolve a Node's conflict.  `rep` is the Node's current representation, if it can be read, and is
+  /// used to name the blocks of the diff at the prompt.
+  pub fn resolve(&self, identifier: &str, diff: &ModelDiff, rep: Option<&Model>) -> Result<ConflictResolutions, ExchangeError> {
+    match self {
+      ConflictPolicy::Interactive => prompt_for_resolution(identifier, diff, rep),
+      ConflictPolicy::KeepRep => Ok(ConflictResolutions::KeepRep),
+      ConflictPolicy::UpdateRep => Ok(ConflictResolutions::UpdateRep),
+      ConflictPolicy::Merge => Ok(ConflictResolutions::Merge),
+      ConflictPolicy::FailFast => Err(ExchangeError::UnresolvedConflict(identifier.to_string())),
+      ConflictPolicy::PerNode { overrides, default } => match overrides.get(identifier) {
+        Some(policy) => policy.resolve(identifier, diff, rep),
+        None => default.resolve(identifier, diff, rep),
+      },
+      ConflictPolicy::Custom(callback) => callback(identifier, diff).ok_or_else(
+        || ExchangeError::UnresolvedConflict(identifier.to_string())
+      ),
+    }
+  }
+}
+
+impl fmt::Debug for ConflictPolicy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConflictPolicy::Interactive => write!(f, "Interactive"),
+      ConflictPolicy::KeepRep => write!(f, "KeepRep"),
+      ConflictPolicy::UpdateRep => write!(f, "UpdateRep"),
+      ConflictPolicy::Merge => write!(f, "Merge"),
+      ConflictPolicy::FailFast => write!(f, "FailFast"),
+      ConflictPolicy::PerNode { overrides, default } => f.debug_struct("PerNode").field("overrides", overrides).field("default", default).finish(),
+      ConflictPolicy::Custom(_) => write!(f, "Custom(..)"),
+    }
+  }
+}
+
+fn prompt_for_resolution(identifier: &str, diff: &ModelDiff, rep: Option<&Model>) -> Result<ConflictResolutions, ExchangeError> {
+  let mut renderer = DiffRenderer::new(DiffFormat::Terminal);
+  if let Some(rep) = rep {
+    renderer = renderer.with_model(rep);
+  }
+  warn!("Conflict detected for Node: {}\n{}", identifier, renderer.render(diff));
+  warn!("To resolve the conflict, select one of the following resolution strategies:");
+  warn!("  (C) - Overwrite the incoming representation with the current representation");
+  warn!("  (i) - Overwrite the current representation with the incoming representation");
+  warn!("  (m) - Merge the changes made to both representations");
+  let mut buffer = String::new();
+  io::stdin().read_line(&mut buffer).expect("Failed to read line");
+  buffer = buffer.trim().to_string();
+  match buffer.to_lowercase().as_str() {
+    "c" => Ok(ConflictResolutions::KeepRep),
+    "i" => Ok(ConflictResolutions::UpdateRep),
+    "m" => Ok(ConflictResolutions::Merge),
+    _ => Err(ExchangeError::InvalidConflictResolution { identifier: identifier.to_string(), input: buffer }),
+  }
+}
+
+/// The file in which a Node keeps the last representation known to agree with its foreign model, as the base of a
+/// `Merge` at startup
+pub fn base_filename(sedaroml_filename: &str) -> String {
+  format!("{}.base.json", sedaroml_filename.strip_suffix(".json").unwrap_or(sedaroml_filename))
+}
+
+/// Records the representation on disk as agreeing with the foreign model
+pub fn save_base(sedaroml_filename: &str) -> Result<(), ModelError> {
+  write_model(&base_filename(sedaroml_filename), &read_model(sedaroml_filename)?)
+}
+
+/// Merges the representation on disk with the foreign model `theirs`, relative to the last saved base.  Without a
+/// saved base the foreign model's changes can't be told apart from the representation's, so `theirs` is taken as is.
+pub fn merge_with_base(identifier: &str, sedaroml_filename: &str, theirs: &Model) -> Result<Model, ModelError> {
+  let ours = read_model(sedaroml_filename)?;
+  let base = read_model(&base_filename(sedaroml_filename)).unwrap_or_else(|_| {
+    warn!("{}: No base representation to merge from.  Taking the incoming representation.", identifier);
+    ours.clone()
+  });
+  let result = Model::merge3(&base, &ours, theirs);
+  for conflict in &result.conflicts {
+    warn!("{}: Merge conflict, keeping the current representation: {}", identifier, conflict);
+  }
+  Ok(result.merged)
+}
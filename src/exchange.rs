@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::thread::sleep;
-use std::{collections::HashMap, path::Path};
+use std::path::Path;
 use std::time::Duration;
 use notify_debouncer_mini::notify::RecommendedWatcher;
 use notify_debouncer_mini::Debouncer;
@@ -9,42 +9,181 @@ use notify_debouncer_mini::{
   new_debouncer, 
   DebounceEventResult,
 };
-use std::sync::{Arc, Mutex};
-use std::{io, panic, thread};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{panic, thread};
 use log::{debug, error, info, warn};
 use crate::commands::{ConflictResolutions, NodeCommands, NodeResponses};
-use crate::model::sedaroml::write_model;
+use crate::conflicts::ConflictPolicy;
+use crate::approval::{prompt_for_approval, ApprovalGate, ApprovalMode, Changeset, Decision};
+use crate::utils::{fingerprint, read_fingerprint, write_json};
+use indexmap::IndexMap;
 use crate::change_queue::{ChangeQueue, QueuedSet};
-use crate::translations::{Translation, OperationFunction};
+use crate::translations::{Translation, TranslationError, OperationFunction};
 use crate::nodes::traits::Exchangeable;
+use crate::graph::TranslationGraph;
+use crate::model::render::{DiffFormat, DiffRenderer};
+use crate::model::sedaroml::{read_model, DiffOptions, Model, ModelDiff, ModelError};
+use crate::round::{ConflictReport, OperationDirection, OperationOutcome, OperationReport, RoundLock, RoundReport, SideEffectReport, TranslationReport};
 use colored::Colorize;
 use std::time::Instant;
+use std::fmt;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExchangeError {
+  /// A Translation's `from` and `to` are the same Node
+  SelfTranslation(String),
+  /// More than one Node uses the same identifier
+  DuplicateIdentifier(String),
+  /// More than one Node uses the same SedaroML filename
+  DuplicateFilename(String),
+  /// More than one Translation is registered between the same pair of Nodes
+  DuplicatePair { from: String, to: String },
+  /// The Translations form a loop through the listed Nodes, which would make propagation nondeterministic
+  Cycle(Vec<String>),
+  /// A Node failed to start or stopped responding during startup
+  NodeStartFailed { identifier: String, filename: String },
+  /// A conflict resolution strategy was not recognized
+  InvalidConflictResolution { identifier: String, input: String },
+  /// A Node failed to carry out a conflict resolution
+  ConflictResolutionFailed(String),
+  /// The conflict policy declined to resolve a Node's conflict
+  UnresolvedConflict(String),
+  /// No Node with the given identifier is registered with the Exchange
+  UnknownNode(String),
+  /// The listed Nodes did not acknowledge `Stop` within the stop timeout
+  NodeStopFailed(Vec<String>),
+  /// No changeset is awaiting approval
+  NoPendingChangeset,
+  /// An Operation of the Translation `from -> to` failed
+  TranslationFailed { from: String, to: String, error: TranslationError },
+  /// The SedaroML file of a Node couldn't be read or written while committing a round
+  FileFailed { identifier: String, error: String },
+  /// The translation thread has stopped, either by shutdown or by `FailurePolicy::Halt`
+  Halted,
+}
+
+impl fmt::Display for ExchangeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ExchangeError::SelfTranslation(iden) => write!(f, "Translation `from` and `to` models must be different: `{}`", iden),
+      ExchangeError::DuplicateIdentifier(iden) => write!(f, "Duplicate model identifier detected: `{}`", iden),
+      ExchangeError::DuplicateFilename(filename) => write!(f, "Duplicate filename detected: `{}`", filename),
+      ExchangeError::DuplicatePair { from, to } => write!(f, "Duplicate translation pair detected: From: `{}`, To: `{}`", from, to),
+      ExchangeError::Cycle(idens) => {
+        let mut loop_idens = idens.iter().map(|iden| format!("`{}`", iden)).collect::<Vec<_>>();
+        if let Some(first) = loop_idens.first().cloned() { loop_idens.push(first); }
+        write!(f, "Cycle detected in translation graph: {}", loop_idens.join(" <-> "))
+      },
+      ExchangeError::NodeStartFailed { identifier, filename } => write!(f, "Failed to start node: `{}` ({})", identifier, filename),
+      ExchangeError::InvalidConflictResolution { identifier, input } => write!(f, "Invalid resolution strategy for node `{}`: `{}`", identifier, input),
+      ExchangeError::ConflictResolutionFailed(iden) => write!(f, "Failed to resolve conflict for node: `{}`", iden),
+      ExchangeError::UnresolvedConflict(iden) => write!(f, "Unresolved conflict for node: `{}`", iden),
+      ExchangeError::UnknownNode(iden) => write!(f, "Unknown model identifier: `{}`", iden),
+      ExchangeError::NodeStopFailed(idens) => {
+        let idens = idens.iter().map(|iden| format!("`{}`", iden)).collect::<Vec<_>>();
+        write!(f, "Failed to stop nodes: {}", idens.join(", "))
+      },
+      ExchangeError::NoPendingChangeset => write!(f, "No changeset is awaiting approval"),
+      ExchangeError::TranslationFailed { from, to, error } => write!(f, "Translation `{}` -> `{}` failed: {}", from, to, error),
+      ExchangeError::FileFailed { identifier, error } => write!(f, "Failed to access SedaroML of node `{}`: {}", identifier, error),
+      ExchangeError::Halted => write!(f, "Exchange has halted"),
+    }
+  }
+}
+
+impl std::error::Error for ExchangeError {}
+
+type Nodes = IndexMap<String, Arc<Mutex<dyn Exchangeable + Sync + Send>>>;
+/// Operations to run from each Node into each of its neighbors, in Translation registration order
+type TranslationsIndex = IndexMap<String, IndexMap<String, Vec<OperationFunction>>>;
+/// Fingerprints of the SedaroML file contents last read or written by the Exchange for each Node
+type Fingerprints = Arc<Mutex<HashMap<String, Option<u64>>>>;
+
+/// What the Exchange does when an Operation returns an error
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+  /// Abort the round, discarding all of its translations so that every Node's representation is left as it was
+  #[default]
+  AbortRound,
+  /// Discard the changes made by the failed Operation and continue with the rest of the round
+  SkipOperation,
+  /// Abort the round and stop running translation rounds
+  Halt,
+}
+
+/// Configuration of an Exchange's behavior
+#[derive(Clone, Debug)]
+pub struct ExchangeOptions {
+  /// How conflicts are resolved, both between a Node's representation and its foreign model at startup and between a
+  /// translation and a concurrent edit to the representation on disk at runtime.  At runtime, `KeepRep` keeps the edit
+  /// on disk (and propagates it in a subsequent round) and `UpdateRep` overwrites it with the translation.
+  pub conflict_policy: ConflictPolicy,
+  /// How long to wait for each Node to acknowledge `Stop` on shutdown
+  pub stop_timeout: Duration,
+  /// Whether each round's changes must be approved before they are written
+  pub approval: ApprovalMode,
+  pub failure_policy: FailurePolicy,
+  /// Which differences count as a change to a Node's representation, e.g. to ignore float round-trip noise
+  pub diff_options: DiffOptions,
+}
+
+impl Default for ExchangeOptions {
+  fn default() -> Self {
+    ExchangeOptions {
+      conflict_policy: ConflictPolicy::default(),
+      stop_timeout: Duration::from_secs(5),
+      approval: ApprovalMode::default(),
+      failure_policy: FailurePolicy::default(),
+      diff_options: DiffOptions::default(),
+    }
+  }
+}
+
+/// A network of Nodes connected by Translations.
+///
+/// Each change to a Node's representation starts a translation round.  A round traverses the translation graph
+/// breadth-first from the changed Node, executing the Translations out of each Node in the order they were registered
+/// with the Exchange.  A Node is translated into at most once per round and the round only continues through a Node
+/// if the Translation into it changed its representation.  Identical inputs therefore always produce identical rounds.
 pub struct Exchange {
   change_queue: ChangeQueue,
-  pub nodes: Arc<Mutex<HashMap<String, Arc<Mutex<dyn Exchangeable + Sync + Send>>>>>,
+  pub nodes: Arc<Mutex<Nodes>>,
   translation_thread: thread::JoinHandle<()>,
   pub watchers: Vec<Debouncer<RecommendedWatcher>>,
+  round_lock: RoundLock,
+  stopping: Arc<AtomicBool>,
+  stop_timeout: Duration,
+  approvals: ApprovalGate,
+  translator: Arc<Translator>,
 }
 impl Exchange {
-  pub fn new(translations: Vec<Translation>) -> Exchange {
+  pub fn new(translations: Vec<Translation>) -> Result<Exchange, ExchangeError> {
+    Exchange::with_options(translations, ExchangeOptions::default())
+  }
+  pub fn with_options(translations: Vec<Translation>, options: ExchangeOptions) -> Result<Exchange, ExchangeError> {
     let startup_time = Instant::now();
     info!("Exchange is in startup...");
     let mut pairs = vec![];
-    let nodes = Arc::new(Mutex::new(HashMap::new()));
+    let nodes = Arc::new(Mutex::new(IndexMap::new()));
     let nodes_clone_for_constructor = nodes.clone();
     let mut nodes = nodes.lock().unwrap();
-    let mut translations_index = HashMap::new();
+    let mut translations_index: TranslationsIndex = IndexMap::new();
     let change_queue = Arc::new(Mutex::new(QueuedSet::new()));
     let change_queue_clone = change_queue.clone();
     let mut filenames = HashSet::new();
+    let mut graph = TranslationGraph::new();
+    let round_lock = RoundLock::new();
+    let round_lock_clone = round_lock.clone();
+    let fingerprints: Fingerprints = Arc::new(Mutex::new(HashMap::new()));
+    let stopping = Arc::new(AtomicBool::new(false));
+    let stop_timeout = options.stop_timeout;
 
     // Validation and setup
     for translation in translations {
       if Arc::ptr_eq(&translation.from, &translation.to) { // This must happen before any locking to prvent deadlock
-        let from = translation.from.clone().lock().unwrap().identifier().clone();
-        let to = translation.to.clone().lock().unwrap().identifier().clone();
-        panic!("Translation `from` and `to` models must be different: Offending model identifiers: `{}` & `{}`", from, to);
+        let iden = translation.from.clone().lock().unwrap().identifier().clone();
+        return Err(ExchangeError::SelfTranslation(iden));
       }
       let from = translation.from.clone(); 
       let from = from.lock().unwrap();
@@ -52,46 +191,36 @@ impl Exchange {
       let to = to.lock().unwrap();
       debug!("Registering translation: from: {}, to: {}", from.identifier(), to.identifier());
       if from.identifier() == to.identifier() {
-        panic!("Translation `from` and `to` models must have different identifiers: `{}` == `{}`", from.identifier(), to.identifier());
+        return Err(ExchangeError::DuplicateIdentifier(from.identifier()));
       }
       let mut pair = vec![from.identifier().clone(), to.identifier().clone()];
       pair.sort_unstable();
       let from_iden = from.identifier().clone();
       let to_iden = to.identifier().clone();
       if nodes.contains_key(&from_iden) && !Arc::ptr_eq(nodes.get(&from_iden).unwrap(), &translation.from) {
-        panic!("Duplicate model identifier detected: `{}`", from_iden);
+        return Err(ExchangeError::DuplicateIdentifier(from_iden));
       }
       if nodes.contains_key(&to_iden) && !Arc::ptr_eq(nodes.get(&to_iden).unwrap(), &translation.to) {
-        panic!("Duplicate model identifier detected: `{}`", to_iden);
+        return Err(ExchangeError::DuplicateIdentifier(to_iden));
       }
       if !nodes.contains_key(&from_iden) && filenames.contains(&from.sedaroml_filename()) {
-        panic!("Duplicate filename detected: `{}`", from.sedaroml_filename());
+        return Err(ExchangeError::DuplicateFilename(from.sedaroml_filename()));
       }
       filenames.insert(from.sedaroml_filename().clone());
       if !nodes.contains_key(&to_iden) && filenames.contains(&to.sedaroml_filename()) {
-        panic!("Duplicate filename detected: `{}`", to.sedaroml_filename());
+        return Err(ExchangeError::DuplicateFilename(to.sedaroml_filename()));
       }
       filenames.insert(to.sedaroml_filename().clone());
       nodes.insert(from_iden.clone(), translation.from);
       nodes.insert(to_iden.clone(), translation.to);
       if pairs.contains(&pair) {
-        panic!("Duplicate translation pair detected: From: `{}`, To: `{}`", from_iden, to_iden);
+        return Err(ExchangeError::DuplicatePair { from: from_iden, to: to_iden });
       }
 
-      // TODO: Cycle detection
+      graph.add_edge(&from_iden, &to_iden);
 
-      if !translations_index.contains_key(&from_iden) {
-        translations_index.insert(from_iden.clone(), HashMap::new());
-      }
-      if !translations_index.get(&from_iden).unwrap().contains_key(&to_iden) {
-        translations_index.get_mut(&from_iden).unwrap().insert(to_iden.clone(), vec![]);
-      }
-      if !translations_index.contains_key(&to_iden) {
-        translations_index.insert(to_iden.clone(), HashMap::new());
-      }
-      if !translations_index.get(&to_iden).unwrap().contains_key(&from_iden) {
-        translations_index.get_mut(&to_iden).unwrap().insert(from_iden.clone(), vec![]);
-      }
+      translations_index.entry(from_iden.clone()).or_default().entry(to_iden.clone()).or_default();
+      translations_index.entry(to_iden.clone()).or_default().entry(from_iden.clone()).or_default();
 
       for op in translation.operations {
         translations_index.get_mut(&from_iden).unwrap().get_mut(&to_iden).unwrap().push(OperationFunction::Forward(op.name.clone(), op.forward));
@@ -100,38 +229,34 @@ impl Exchange {
 
       pairs.push(pair);
     }
+    if let Some(cycle) = graph.find_cycle() {
+      return Err(ExchangeError::Cycle(cycle));
+    }
 
     // Start all nodes and read in their representations
     for node in nodes.values() {
       let mut node = node.lock().unwrap();
-      match node.tx_to_node_blocking(NodeCommands::Start) {
+      let start_failed = |node: &dyn Exchangeable| ExchangeError::NodeStartFailed { identifier: node.identifier(), filename: node.sedaroml_filename() };
+      match node.try_tx_to_node_blocking(NodeCommands::Start).map_err(|_| start_failed(&*node))? {
         NodeResponses::Conflict(diff) => {
-          warn!("Conflict detected for Node: {}\n{:?}", node.identifier(), diff);
-          warn!("To resolve the conflict, select one of the following resolution strategies:");
-          warn!("  (C) - Overwrite the incoming representation with the current representation");
-          warn!("  (i) - Overwrite the current representation with the incoming representation");
-          let mut buffer = String::new();
-          io::stdin().read_line(&mut buffer).expect("Failed to read line");
-          buffer = buffer.trim().to_string();
-          let resolution_result = match buffer.to_lowercase().as_str() {
-            "c" => { node.tx_to_node_blocking(NodeCommands::ResolveConflict(ConflictResolutions::KeepRep)) },
-            "i" => { node.tx_to_node_blocking(NodeCommands::ResolveConflict(ConflictResolutions::UpdateRep)) },
-            _ => { panic!("Invalid resolution strategy: `{}`", buffer); },
-          };
-          match resolution_result {
-            NodeResponses::ConflictResolved(elapsed) => { info!("Conflict resolved. {:.2}s", elapsed.as_secs_f64()); },
-            _ => { panic!("Failed to resolve conflict for node: {}", node.identifier()); },
+          let rep = read_model(&node.sedaroml_filename()).ok();
+          let resolution = options.conflict_policy.resolve(&node.identifier(), &diff, rep.as_ref())?;
+          info!("Resolving conflict for Node: {} ({:?})", node.identifier(), resolution);
+          match node.try_tx_to_node_blocking(NodeCommands::ResolveConflict(resolution)) {
+            Ok(NodeResponses::ConflictResolved(elapsed)) => { info!("Conflict resolved. {:.2}s", elapsed.as_secs_f64()); },
+            _ => { return Err(ExchangeError::ConflictResolutionFailed(node.identifier())); },
           }
           // Wait for start response.  This commanding is getting a bit out of hand.
-          match node.rx_from_node() {
-            NodeResponses::Started => {},
-            _ => { panic!("Failed to start node: {}", node.identifier()) }
+          match node.try_rx_from_node() {
+            Ok(NodeResponses::Started) => {},
+            _ => { return Err(start_failed(&*node)) }
           }
         },
         NodeResponses::Started => {},
-        _ => { panic!("Failed to start node: {}", node.identifier()) }
+        _ => { return Err(start_failed(&*node)) }
       }
       node.refresh_rep();
+      fingerprints.lock().unwrap().insert(node.identifier(), read_fingerprint(&node.sedaroml_filename()));
     }
 
     // Bind watchers for models
@@ -139,170 +264,515 @@ impl Exchange {
     let queue = change_queue;
     for model in nodes.values_mut() {
       let model = model.lock().unwrap();
-      let debouncer = setup_file_watcher(model.identifier(), model.sedaroml_filename(), queue.clone());
+      let debouncer = setup_file_watcher(model.identifier(), model.sedaroml_filename(), queue.clone(), fingerprints.clone());
       watchers.push(debouncer);
     }
 
-    let nodes_clone = nodes.clone();
+    let approvals = ApprovalGate::new();
+    let translator = Arc::new(Translator {
+      nodes: nodes.clone(),
+      translations_index,
+      fingerprints,
+      change_queue: queue.clone(),
+      options,
+      approvals: approvals.clone(),
+      stopping: stopping.clone(),
+    });
+    let translator_clone = translator.clone();
     let handler = thread::spawn(move || {
-      let mut nodes = nodes_clone;
-      let mut visited_nodes = HashSet::new();
-      let mut changed_nodes = HashSet::new();
-      let mut round_time: Option<Instant> = None;
+      let translator = translator_clone;
+      // Wakes anything waiting on a round however the thread exits, including by panicking
+      let _halt_on_exit = HaltOnExit(round_lock.clone());
       loop {
-        let queue = queue.clone();
-        let mut queue = queue.lock().unwrap();
-        let change = queue.dequeue();
-        drop(queue); // Release lock so other threads can enqueue
-        if let Some(change) = change {
-          if round_time.is_none() {
-            round_time = Some(Instant::now());
+        // Mark the round as started before releasing the queue, so that a change enqueued after this one was dequeued
+        // is never taken to be consumed by this round (see `Exchange::enqueue`)
+        let change = {
+          let mut queue = queue.lock().unwrap();
+          let change = queue.dequeue();
+          if change.is_some() {
+            round_lock.acquire();
           }
-          let change = change.clone();
+          change
+        };
+        if let Some(change) = change {
           info!("{} {}", "Change:".cyan(), change);
-          visited_nodes.insert(change.clone());
-          let translation = translations_index.get(&change).unwrap();
-          let from = nodes.get(&change).unwrap().clone();
-          let mut from = from.lock().unwrap();
-
-          if !translation.is_empty() { // Optimization
-            from.refresh_rep(); // Refresh the model from disk
-          }
-
-          for (to_iden, operations) in translation { // TODO: Make this order deterministic
-            if visited_nodes.contains(&to_iden.clone()) {
-              info!("  No dependent translations remaining.");
-              continue;
-            }
-
-            let to = nodes.get_mut(&to_iden.clone()).unwrap().clone();
-            let mut to = to.lock().unwrap();
-            let to_rep_clone = to.rep().clone();
-            let mut to_rep_clone_for_logs = to_rep_clone.clone();
-            for operation in operations {
-              match operation {
-                OperationFunction::Forward(op_name, op) => {
-                  match op(&from.rep(), &mut to.rep_mut()) {
-                    Ok(_) => {
-                      let arrow = match op_name {
-                        Some(op_name) => format!("--({})-->", op_name),
-                        None => "-->".into(),
-                      };
-                      let result_str = if to_rep_clone_for_logs.diff(to.rep()).is_empty() {
-                        "Unchanged".yellow()
-                      } else {
-                        "Changed".green()
-                      };
-                      info!("  Translation: {} {} {}: {}", from.identifier(), arrow, to.identifier(), result_str);
-                    },
-                    Err(e) => panic!("Translation {} -> {} failed: {:?}", from.identifier(), to.identifier(), e),
-                  }
-                },
-                OperationFunction::Reverse(op_name, op) => {
-                  match op(&from.rep(), &mut to.rep_mut()) {
-                    Ok(_) => {
-                      let arrow = match op_name {
-                        Some(op_name) => format!("--({})^-1-->", op_name),
-                        None => "-->".into(),
-                      };
-                      let result_str = if to_rep_clone_for_logs.diff(to.rep()).is_empty() {
-                        "Unchanged".yellow()
-                      } else {
-                        "Changed".green()
-                      };
-                      info!("  Translation: {} {} {}: {}", from.identifier(), arrow, to.identifier(), result_str);
-                    },
-                    Err(e) => panic!("Translation {} -> {} failed: {:?}", from.identifier(), to.identifier(), e),
-                  }
-                },
-              }
-              to_rep_clone_for_logs = to.rep().clone();
-            }
-            // Write model and notify node that its translation is complete in the current round
-            // Note that its important that a translation into a node only ever happen from one other node in a given round
-            // not from > 1.  
-
-            let to_diff = to_rep_clone.diff(to.rep());
-            if !to_diff.is_empty() { 
-              changed_nodes.insert(to_iden.clone());
-              write_model(&to.sedaroml_filename(), &to.rep()).unwrap_or_else(
-                |e| panic!("Failed to write model to file: {}: {:?}", to.sedaroml_filename(), e)
-              );
-              to.tx_to_node(NodeCommands::Changed(to_diff));
-            } else {
-              handle_unchanged(&to_iden, &mut visited_nodes, &translations_index); // Recursively add all deps to visited
-            }
-            to.tx_to_node(NodeCommands::Done);
-          }
-
-          // Drop locked model (so it can be locked again below if need by during round close-out)
-          drop(from);
-
-          // If every node has been visited, translation round is complete
-          if visited_nodes.len() == nodes.len() {
-            if !changed_nodes.is_empty() {
-              info!("Waiting for node side-effects to complete...");
-              let mut heard_from = HashSet::new();
-              let changed_nodes_locked = changed_nodes.iter().map(|iden| nodes.get(iden).unwrap().lock().unwrap()).collect::<Vec<_>>();
-              while heard_from.len() < changed_nodes.len() {
-                for node in &changed_nodes_locked {
-                  if !heard_from.contains(&node.identifier()) {
-                    match node.rx_from_node_timeout(Duration::from_millis(10)) {
-                      Ok(NodeResponses::Done(t)) => { 
-                        heard_from.insert(node.identifier().clone());
-                        info!("  {}: {} {:.2}s", node.identifier(), "Done".green(), t.as_secs_f64()) 
-                      },
-                      _ => {},
-                    }
-                  }
-                }
-              }
-            }
-            let elapsed = match round_time {
-              Some(round_time) => format!("{:.2}s", round_time.elapsed().as_secs_f64()),
-              None => "".into(),
-            };
-            info!("{} {}", "Translation complete.".purple(), elapsed);
-            round_time = None;
-            visited_nodes.clear();
-            changed_nodes.clear();
+          let report = translator.run_round(&change, round_lock.completed() + 1);
+          info!("{} {:.2}s", "Translation complete.".purple(), report.elapsed.as_secs_f64());
+          let halt = report.aborted && translator.options.failure_policy == FailurePolicy::Halt;
+          round_lock.release(report);
+          if halt {
+            error!("Exchange halted.");
+            break;
           }
+        } else if translator.stopping.load(Ordering::SeqCst) {
+          break; // The queue has been drained
         } else {
           sleep(Duration::from_millis(10));
         }
       }
     });
     info!("{} {:.2}s", "Ready.".green(), startup_time.elapsed().as_secs_f64());
-    Exchange {
+    Ok(Exchange {
       change_queue: change_queue_clone,
       nodes: nodes_clone_for_constructor,
       translation_thread: handler,
       watchers,
-    }
+      round_lock: round_lock_clone,
+      stopping,
+      stop_timeout,
+      approvals,
+      translator,
+    })
   }
+  /// Blocks until Ctrl+C is received (or the translation thread exits) and then shuts the Exchange down
   pub fn wait(self) {
-    self.translation_thread.join().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+      let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+      if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+        tx.send(()).ok();
+      }
+    });
+    while !self.translation_thread.is_finished() {
+      if rx.recv_timeout(Duration::from_millis(100)).is_ok() {
+        info!("Received Ctrl+C.");
+        break;
+      }
+    }
+    if let Err(e) = self.shutdown() {
+      error!("{}", e);
+    }
+  }
+  /// Gracefully shuts the Exchange down.
+  ///
+  /// File watchers are dropped so that no new changes are queued, changes already queued are translated, and then every
+  /// Node is sent `Stop`.  Node threads are joined once they acknowledge with `Stopped`; Nodes that don't within the
+  /// stop timeout are reported in the returned error.
+  pub fn shutdown(mut self) -> Result<(), ExchangeError> {
+    info!("Exchange is shutting down...");
+    self.watchers.clear();
+    self.stopping.store(true, Ordering::SeqCst);
+    if self.translation_thread.join().is_err() {
+      error!("Translation thread panicked.");
+    }
+    let mut failed = vec![];
+    for node in self.nodes.lock().unwrap().values() {
+      let mut node = node.lock().unwrap();
+      node.tx().send(NodeCommands::Stop).ok();
+      let deadline = Instant::now() + self.stop_timeout;
+      let mut stopped = false;
+      while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+        match node.rx_from_node_timeout(timeout) {
+          Ok(NodeResponses::Stopped) => { stopped = true; break; },
+          Ok(_) => {}, // Discard responses that arrived after the last round
+          Err(_) => break,
+        }
+      }
+      if stopped {
+        if let Some(handle) = node.take_thread() {
+          if handle.join().is_err() { warn!("{}: Node thread panicked.", node.identifier()); }
+        }
+        info!("  {}: {}", node.identifier(), "Stopped".green());
+      } else {
+        warn!("  {}: {}", node.identifier(), "Failed to stop".red());
+        failed.push(node.identifier());
+      }
+    }
+    if !failed.is_empty() {
+      return Err(ExchangeError::NodeStopFailed(failed));
+    }
+    info!("{}", "Exchange stopped.".green());
+    Ok(())
+  }
+  pub fn trigger_watch_for_model(&self, iden: String) -> Result<(), ExchangeError> {
+    self.enqueue(iden).map(|_| ())
+  }
+  /// Enqueues a change to the Node `iden` and returns the number of rounds started before it was enqueued.  The change
+  /// is consumed by the first later round triggered by `iden`.
+  fn enqueue(&self, iden: String) -> Result<u64, ExchangeError> {
+    if !self.nodes.lock().unwrap().contains_key(&iden) {
+      return Err(ExchangeError::UnknownNode(iden));
+    }
+    if self.translation_thread.is_finished() {
+      return Err(ExchangeError::Halted);
+    }
+    let mut queue = self.change_queue.lock().unwrap();
+    queue.enqueue(iden);
+    Ok(self.round_lock.started())
+  }
+  /// Lock held by the translation thread for the duration of each translation round
+  pub fn round_lock(&self) -> &RoundLock {
+    &self.round_lock
+  }
+  /// Reports of the most recently completed translation rounds, oldest first
+  pub fn round_reports(&self) -> Vec<RoundReport> {
+    self.round_lock.reports()
+  }
+  pub fn last_round_report(&self) -> Option<RoundReport> {
+    self.round_lock.last_report()
+  }
+  /// Blocks until the translation round in progress (if any) is complete and returns the report of the last round
+  pub fn wait_for_round(&self) -> Result<Option<RoundReport>, ExchangeError> {
+    self.round_lock.wait()
+  }
+  pub async fn wait_for_round_async(&self) -> Result<Option<RoundReport>, ExchangeError> {
+    self.round_lock.wait_async().await
+  }
+  /// Triggers a translation round from the Node `iden` and blocks until the round that consumes the change is complete
+  pub fn trigger_and_wait(&self, iden: &str) -> Result<RoundReport, ExchangeError> {
+    let after = self.enqueue(iden.to_string())?;
+    self.round_lock.wait_for_change(iden, after)
+  }
+  pub async fn trigger_and_wait_async(&self, iden: &str) -> Result<RoundReport, ExchangeError> {
+    let after = self.enqueue(iden.to_string())?;
+    self.round_lock.wait_for_change_async(iden, after).await
   }
-  pub fn trigger_watch_for_model(&self, iden: String) {
-    // TODO: Validate that iden is a valid model identifier
-    self.change_queue.lock().unwrap().enqueue(iden.to_string());
+  /// Changeset of the round awaiting approval, if any.  Only used with `ApprovalMode::Manual`.
+  pub fn pending_changeset(&self) -> Option<Changeset> {
+    self.approvals.pending()
   }
+  /// Blocks until a changeset is awaiting approval and returns it
+  pub fn wait_for_changeset(&self) -> Changeset {
+    self.approvals.wait_for_pending()
+  }
+  pub async fn wait_for_changeset_async(&self) -> Changeset {
+    self.approvals.wait_for_pending_async().await
+  }
+  /// Approves the pending changeset and blocks until its changes have been written and the round is complete
+  pub fn approve(&self) -> Result<RoundReport, ExchangeError> {
+    self.decide(Decision::Approve)
+  }
+  /// Rejects the pending changeset, leaving the Nodes' representations unchanged, and returns the report of its round
+  pub fn reject(&self) -> Result<RoundReport, ExchangeError> {
+    self.decide(Decision::Reject)
+  }
+  /// Computes the diffs a round would produce in each Node if the representation of the Node `iden` were `model`.
+  ///
+  /// The round is traversed exactly as the translation thread would, but on copies of the Nodes' representations, so
+  /// nothing is written to disk and no Node is notified.
+  pub fn dry_run(&self, iden: &str, model: Model) -> Result<IndexMap<String, ModelDiff>, ExchangeError> {
+    if !self.translator.nodes.contains_key(iden) {
+      return Err(ExchangeError::UnknownNode(iden.to_string()));
+    }
+    info!("{} {}", "Dry run:".cyan(), iden);
+    let propagation = self.translator.propagate(iden, model);
+    if let Some(e) = propagation.failure {
+      return Err(e);
+    }
+    Ok(propagation.translations.into_iter().filter_map(|t| Some((t.to, t.diff?))).collect())
+  }
+  fn decide(&self, decision: Decision) -> Result<RoundReport, ExchangeError> {
+    let changeset = self.approvals.decide(decision).ok_or(ExchangeError::NoPendingChangeset)?;
+    self.round_lock.wait_for_change(&changeset.trigger, changeset.round - 1)
+  }
+}
+
+
+/// Marks the round lock as halted when dropped by the exiting translation thread
+struct HaltOnExit(RoundLock);
+
+impl Drop for HaltOnExit {
+  fn drop(&mut self) {
+    self.0.halt();
+  }
+}
+
+/// Result of executing a round's Translations on copies of the Nodes' representations
+struct Propagation {
+  translations: Vec<TranslationReport>,
+  /// Translated representation of each Node changed by the round
+  models: HashMap<String, Model>,
+  /// The failure that aborted the propagation, if any
+  failure: Option<ExchangeError>,
+}
+
+/// State used by the translation thread to execute translation rounds
+struct Translator {
+  nodes: Nodes,
+  translations_index: TranslationsIndex,
+  fingerprints: Fingerprints,
+  change_queue: ChangeQueue,
+  options: ExchangeOptions,
+  approvals: ApprovalGate,
+  stopping: Arc<AtomicBool>,
 }
 
+impl Translator {
+  /// Executes the translation round triggered by a change to the Node `trigger`.  See `Exchange` for the traversal order.
+  fn run_round(&self, trigger: &str, round: u64) -> RoundReport {
+    let round_time = Instant::now();
+    let mut report = RoundReport::new(trigger.to_string());
+    let mut from = self.nodes.get(trigger).unwrap().lock().unwrap();
+    from.refresh_rep(); // Refresh the model from disk
+    self.fingerprints.lock().unwrap().insert(from.identifier(), read_fingerprint(&from.sedaroml_filename()));
+    let trigger_rep = from.rep().clone();
+    drop(from);
+
+    let Propagation { translations, mut models, failure } = self.propagate(trigger, trigger_rep);
+    report.translations = translations;
+    if let Some(e) = failure {
+      error!("  {}.  Round aborted.", e);
+      report.translations.iter_mut().for_each(|t| t.diff = None);
+      models.clear();
+      report.aborted = true;
+      report.errors.push(e.to_string());
+    }
+    if self.options.approval != ApprovalMode::Disabled && !models.is_empty() {
+      let changeset = Changeset {
+        round,
+        trigger: trigger.to_string(),
+        diffs: report.translations.iter().filter_map(|t| Some((t.to.clone(), t.diff.clone()?))).collect(),
+      };
+      let decision = match self.options.approval {
+        ApprovalMode::Interactive => {
+          let reps = changeset.diffs.keys()
+            .map(|iden| (iden.clone(), self.nodes.get(iden).unwrap().lock().unwrap().rep().clone())).collect();
+          Some(prompt_for_approval(&changeset, &reps))
+        },
+        _ => {
+          info!("  Waiting for approval...");
+          self.approvals.request(changeset, || self.stopping.load(Ordering::SeqCst))
+        },
+      };
+      let approved = decision == Some(Decision::Approve);
+      info!("  Changes {}", if approved { "approved".green() } else { "rejected".red() });
+      if !approved {
+        report.translations.iter_mut().for_each(|t| t.diff = None);
+        models.clear();
+      }
+      report.approved = Some(approved);
+    }
+    self.commit(&mut report, models);
+    report.elapsed = round_time.elapsed();
+    report
+  }
+
+  /// Executes the Translations out of `trigger`, whose representation is `trigger_rep`, without modifying any Node
+  fn propagate(&self, trigger: &str, trigger_rep: Model) -> Propagation {
+    let mut translations = vec![];
+    let mut reps = HashMap::from([(trigger.to_string(), trigger_rep)]);
+    let mut visited_nodes = HashSet::from([trigger.to_string()]);
+    let mut queue = VecDeque::from([trigger.to_string()]);
+    let mut failure = None;
+
+    'round: while let Some(from_iden) = queue.pop_front() {
+      let from_rep = reps.get(&from_iden).unwrap().clone();
+      for (to_iden, operations) in self.translations_index.get(&from_iden).unwrap() {
+        if !visited_nodes.insert(to_iden.clone()) {
+          continue;
+        }
+
+        let to_rep_original = self.nodes.get(to_iden).unwrap().lock().unwrap().rep().clone();
+        let mut to_rep = to_rep_original.clone();
+        let mut translation_report = TranslationReport {
+          from: from_iden.clone(),
+          to: to_iden.clone(),
+          operations: vec![],
+          diff: None,
+        };
+        for operation in operations {
+          let (direction, op_name, op) = match operation {
+            OperationFunction::Forward(op_name, op) => (OperationDirection::Forward, op_name, op),
+            OperationFunction::Reverse(op_name, op) => (OperationDirection::Reverse, op_name, op),
+          };
+          let to_rep_for_logs = to_rep.clone();
+          let arrow = match (op_name, direction) {
+            (Some(op_name), OperationDirection::Forward) => format!("--({})-->", op_name),
+            (Some(op_name), OperationDirection::Reverse) => format!("--({})^-1-->", op_name),
+            (None, _) => "-->".into(),
+          };
+          match op(&from_rep, &mut to_rep) {
+            Ok(_) => {
+              let (outcome, result_str) = if to_rep_for_logs.diff_with(&to_rep, &self.options.diff_options).is_empty() {
+                (OperationOutcome::Unchanged, "Unchanged".yellow())
+              } else {
+                (OperationOutcome::Changed, "Changed".green())
+              };
+              info!("  Translation: {} {} {}: {}", from_iden, arrow, to_iden, result_str);
+              translation_report.operations.push(OperationReport { name: op_name.clone(), direction, outcome });
+            },
+            Err(e) => {
+              error!("  Translation: {} {} {}: {} {}", from_iden, arrow, to_iden, "Failed".red(), e);
+              translation_report.operations.push(OperationReport { name: op_name.clone(), direction, outcome: OperationOutcome::Failed(e.clone()) });
+              to_rep = to_rep_for_logs;
+              if self.options.failure_policy != FailurePolicy::SkipOperation {
+                failure = Some(ExchangeError::TranslationFailed { from: from_iden.clone(), to: to_iden.clone(), error: e });
+                translations.push(translation_report);
+                break 'round;
+              }
+            },
+          }
+        }
+
+        // Nodes beyond an unchanged node are not traversed.  The diff options only decide whether the Node changed:
+        // the Node is sent every difference, so that its foreign model matches the representation written to disk.
+        if !to_rep_original.diff_with(&to_rep, &self.options.diff_options).is_empty() {
+          let to_diff = to_rep_original.diff(&to_rep);
+          // Plain, so that log files and other non-terminal output don't collect color codes
+          let rendered = DiffRenderer::new(DiffFormat::Plain).with_model(&to_rep_original).render(&to_diff);
+          info!("    {}", rendered.replace('\n', "\n    "));
+          translation_report.diff = Some(to_diff);
+          reps.insert(to_iden.clone(), to_rep);
+          queue.push_back(to_iden.clone());
+        }
+        translations.push(translation_report);
+      }
+    }
+    reps.remove(trigger);
+    Propagation { translations, models: reps, failure }
+  }
+
+  /// Writes the translated representations to the Nodes' files, notifies the Nodes that their translations are
+  /// complete, and waits for the side-effects of the changed Nodes
+  fn commit(&self, report: &mut RoundReport, mut models: HashMap<String, Model>) {
+    let mut changed_nodes = vec![];
+    let mut discarded = HashSet::new();
+    for translation in &mut report.translations {
+      let mut to = self.nodes.get(&translation.to).unwrap().lock().unwrap();
+      let Some(to_diff) = translation.diff.take() else {
+        to.tx_to_node(NodeCommands::Done);
+        continue;
+      };
+      if report.aborted || discarded.contains(&translation.from) {
+        // Translated from a representation that was discarded, or left uncommitted by a failure
+        discarded.insert(translation.to.clone());
+        to.tx_to_node(NodeCommands::Done);
+        continue;
+      }
+      let translated = models.remove(&translation.to).unwrap();
+      // Resolution is repeated if the file is edited again before the resolved representation is written
+      let result: Result<(), ExchangeError> = 'commit: loop {
+        let (edit, on_disk_fingerprint) = match detect_concurrent_edit(&*to, &translated, &self.fingerprints, &self.options.diff_options) {
+          Ok(detected) => detected,
+          Err(e) => break 'commit Err(e),
+        };
+        let resolution = edit.map(|diff| {
+          let resolution = self.options.conflict_policy.resolve(&translation.to, &diff, Some(to.rep())).unwrap_or_else(|e| {
+            error!("{}.  Keeping the representation on disk.", e);
+            ConflictResolutions::KeepRep
+          });
+          info!("  Concurrent edit to {}: {:?}", translation.to, resolution);
+          report.conflicts.push(ConflictReport { node: translation.to.clone(), diff, resolution, merge_conflicts: vec![] });
+          resolution
+        });
+        if resolution == Some(ConflictResolutions::KeepRep) {
+          // Discard the translation and propagate the edit on disk in a subsequent round instead
+          to.refresh_rep();
+          self.fingerprints.lock().unwrap().insert(translation.to.clone(), read_fingerprint(&to.sedaroml_filename()));
+          self.change_queue.lock().unwrap().enqueue(translation.to.clone());
+          discarded.insert(translation.to.clone());
+          break 'commit Ok(());
+        }
+        let (model, to_diff) = if resolution == Some(ConflictResolutions::Merge) {
+          // Combine the translation with the edit on disk, and propagate the edit in a subsequent round
+          let on_disk = match read_model(&to.sedaroml_filename()) {
+            Ok(on_disk) => on_disk,
+            Err(e) => break 'commit Err(file_failed(&translation.to, e)),
+          };
+          let result = Model::merge3(to.rep(), &on_disk, &translated);
+          for conflict in &result.conflicts {
+            warn!("  Merge conflict in {}, keeping the edit on disk: {}", translation.to, conflict);
+          }
+          report.conflicts.last_mut().unwrap().merge_conflicts = result.conflicts;
+          self.change_queue.lock().unwrap().enqueue(translation.to.clone());
+          // Keep the file as it is if the merge only differs from it by what the diff options ignore
+          let merged = if on_disk.diff_with(&result.merged, &self.options.diff_options).is_empty() { on_disk.clone() } else { result.merged };
+          let to_diff = on_disk.diff(&merged);
+          (merged, to_diff)
+        } else {
+          (translated.clone(), to_diff.clone())
+        };
+        let contents = model.to_pretty_string();
+        // Hold the fingerprints lock while writing so the watcher can't read a partially written file and can ignore
+        // the exchange's own write
+        let mut fingerprints = self.fingerprints.lock().unwrap();
+        if read_fingerprint(&to.sedaroml_filename()) != on_disk_fingerprint {
+          // Edited while the conflict was being resolved, e.g. at the prompt
+          drop(fingerprints);
+          warn!("  {} was edited again before the translation was written", translation.to);
+          continue;
+        }
+        let previous = fingerprints.insert(translation.to.clone(), Some(fingerprint(&contents)));
+        if let Err(e) = write_json(&to.sedaroml_filename(), &contents) {
+          fingerprints.insert(translation.to.clone(), previous.flatten());
+          break 'commit Err(file_failed(&translation.to, e));
+        }
+        drop(fingerprints);
+        *to.rep_mut() = model;
+        // A merge that kept the whole edit leaves nothing for the Node to reconcile
+        if !to_diff.is_empty() {
+          translation.diff = Some(to_diff.clone());
+          to.tx_to_node(NodeCommands::Changed(to_diff));
+          changed_nodes.push(translation.to.clone());
+        }
+        break 'commit Ok(());
+      };
+      if let Err(e) = result {
+        discarded.insert(translation.to.clone());
+        report.errors.push(e.to_string());
+        if self.options.failure_policy == FailurePolicy::SkipOperation {
+          error!("  {}.  Translation discarded.", e);
+        } else {
+          error!("  {}.  Round aborted.", e);
+          report.aborted = true;
+        }
+      }
+      to.tx_to_node(NodeCommands::Done);
+    }
+
+    if !changed_nodes.is_empty() {
+      info!("Waiting for node side-effects to complete...");
+      let mut heard_from = HashSet::new();
+      let changed_nodes_locked = changed_nodes.iter().map(|iden| self.nodes.get(iden).unwrap().lock().unwrap()).collect::<Vec<_>>();
+      while heard_from.len() < changed_nodes.len() {
+        for node in &changed_nodes_locked {
+          if !heard_from.contains(&node.identifier()) {
+            if let Ok(NodeResponses::Done(t)) = node.rx_from_node_timeout(Duration::from_millis(10)) {
+              heard_from.insert(node.identifier().clone());
+              info!("  {}: {} {:.2}s", node.identifier(), "Done".green(), t.as_secs_f64());
+              report.side_effects.push(SideEffectReport { node: node.identifier(), elapsed: t });
+            }
+          }
+        }
+      }
+    }
+  }
+}
 
-fn handle_unchanged(iden: &str, visited: &mut HashSet<String>, translations: &HashMap<String, HashMap<String, Vec<OperationFunction>>>) {
-  if visited.contains(iden) { return; }
-  visited.insert(iden.to_string());
-  for (to_iden, _) in translations.get(iden).unwrap() {
-    handle_unchanged(to_iden, visited, translations);
+/// Returns the diff from a Node's representation on disk to its `translated` representation if the file has been
+/// edited since the Exchange last read or wrote it, along with the fingerprint of the file that was compared
+fn detect_concurrent_edit(
+  node: &dyn Exchangeable, translated: &Model, fingerprints: &Fingerprints, options: &DiffOptions,
+) -> Result<(Option<ModelDiff>, Option<u64>), ExchangeError> {
+  // Hold the fingerprints lock so the watcher can't record the edit while it's being read
+  let fingerprints = fingerprints.lock().unwrap();
+  let on_disk_fingerprint = read_fingerprint(&node.sedaroml_filename());
+  if on_disk_fingerprint == fingerprints.get(&node.identifier()).copied().flatten() {
+    return Ok((None, on_disk_fingerprint));
   }
+  let on_disk = read_model(&node.sedaroml_filename()).map_err(|e| file_failed(&node.identifier(), e))?;
+  if node.rep().diff_with(&on_disk, options).is_empty() {
+    return Ok((None, on_disk_fingerprint)); // Cosmetic edit
+  }
+  Ok((Some(on_disk.diff(translated)), on_disk_fingerprint))
+}
+
+fn file_failed(identifier: &str, error: ModelError) -> ExchangeError {
+  ExchangeError::FileFailed { identifier: identifier.to_string(), error: format!("{:?}", error) }
 }
 
-fn setup_file_watcher(identifier: String, path: String, queue: ChangeQueue) -> Debouncer<RecommendedWatcher> {
+fn setup_file_watcher(identifier: String, path: String, queue: ChangeQueue, fingerprints: Fingerprints) -> Debouncer<RecommendedWatcher> {
   let identifier = identifier.clone();
+  let watched_path = path.clone();
   let mut debouncer = new_debouncer(Duration::from_millis(5), move |res: DebounceEventResult| {
     match res {
-      Ok(_event) => { queue.lock().unwrap().enqueue(identifier.to_string()) },
+      Ok(_event) => {
+        // Ignore events that didn't change the contents the exchange last saw, such as the exchange's own writes
+        let fingerprints = fingerprints.lock().unwrap();
+        if read_fingerprint(&watched_path) != fingerprints.get(&identifier).copied().flatten() {
+          queue.lock().unwrap().enqueue(identifier.to_string())
+        }
+      },
       Err(e) => error!("watch error: {:?}", e),
     }
   }).unwrap_or_else(|_| panic!("Failed to create debouncer"));
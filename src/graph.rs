@@ -0,0 +1,66 @@
+use indexmap::IndexMap;
+
+/// Undirected graph of the Nodes in an Exchange, where each edge is a Translation between two Nodes.
+///
+/// Nodes and neighbors are kept in Translation registration order so that traversals of the graph are deterministic.
+#[derive(Debug, Clone, Default)]
+pub struct TranslationGraph {
+  adjacency: IndexMap<String, Vec<String>>,
+}
+
+impl TranslationGraph {
+  pub fn new() -> TranslationGraph {
+    TranslationGraph { adjacency: IndexMap::new() }
+  }
+
+  pub fn add_edge(&mut self, a: &str, b: &str) {
+    let neighbors = self.adjacency.entry(a.to_string()).or_default();
+    if !neighbors.iter().any(|n| n == b) { neighbors.push(b.to_string()); }
+    let neighbors = self.adjacency.entry(b.to_string()).or_default();
+    if !neighbors.iter().any(|n| n == a) { neighbors.push(a.to_string()); }
+  }
+
+  pub fn nodes(&self) -> impl Iterator<Item = &String> {
+    self.adjacency.keys()
+  }
+
+  pub fn neighbors(&self, iden: &str) -> &[String] {
+    match self.adjacency.get(iden) {
+      Some(neighbors) => neighbors,
+      None => &[],
+    }
+  }
+
+  /// Returns the Nodes on the first cycle found in the graph, in traversal order, or `None` if the graph is a forest.
+  ///
+  /// A bidirectional Translation `A <-> B` is a single undirected edge and is not considered a cycle.
+  pub fn find_cycle(&self) -> Option<Vec<String>> {
+    let mut visited = vec![];
+    for iden in self.adjacency.keys() {
+      if visited.contains(iden) { continue; }
+      let mut path = vec![];
+      if let Some(cycle) = self.find_cycle_from(iden, None, &mut visited, &mut path) {
+        return Some(cycle);
+      }
+    }
+    None
+  }
+
+  fn find_cycle_from(&self, iden: &String, parent: Option<&String>, visited: &mut Vec<String>, path: &mut Vec<String>) -> Option<Vec<String>> {
+    visited.push(iden.clone());
+    path.push(iden.clone());
+    for neighbor in self.neighbors(iden) {
+      if Some(neighbor) == parent { continue; }
+      if let Some(start) = path.iter().position(|n| n == neighbor) {
+        // In an undirected depth-first search, any non-tree edge leads back to an ancestor on the current path
+        return Some(path[start..].to_vec());
+      }
+      if visited.contains(neighbor) { continue; }
+      if let Some(cycle) = self.find_cycle_from(neighbor, Some(iden), visited, path) {
+        return Some(cycle);
+      }
+    }
+    path.pop();
+    None
+  }
+}
@@ -5,6 +5,11 @@ pub mod utils;
 pub mod metadata;
 pub mod nodes;
 pub mod translations;
+pub mod graph;
+pub mod round;
 pub mod exchange;
+pub mod conflicts;
+pub mod approval;
+pub mod query;
 mod tests;
 pub mod commands;
\ No newline at end of file
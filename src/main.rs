@@ -3,17 +3,24 @@ use modex::nodes::cosimulation::{Cosimulation, SimulationJobId};
 use modex::nodes::sedaroml::SedaroML;
 use serde_json::Value;
 use modex::logging::init_logger;
-use modex::model::sedaroml::Model;
+use modex::model::render::{DiffFormat, DiffRenderer};
+use modex::model::sedaroml::{read_model, Model};
 use modex::nodes::sedaro::{Sedaro, SedaroCredentials};
 use modex::nodes::excel::Excel;
 use modex::exchange::Exchange;
-use modex::translations::{Operation, Translation};
+use modex::translations::{BlockSelector, FieldMapping, FieldRef, Operation, Translation};
 use modex::utils::read_json;
 
 
 #[tokio::main]
 async fn main() {
   init_logger().expect("Failed to initialize logger.");
+
+  let args = std::env::args().collect::<Vec<_>>();
+  if args.get(1).map(String::as_str) == Some("diff") {
+    diff_command(&args[2..]);
+    return;
+  }
   
   let secrets = read_json("secrets.json").expect("Failed to read secrets.json");
   let api_key = secrets.get("ALPHA").unwrap().as_str().unwrap();
@@ -36,74 +43,41 @@ async fn main() {
   );
   let test = SedaroML::new("test.json".into(), "test.json".into());
 
-  let excel_to_sedaroml = Operation {
-    name: Some("-".into()),
-    forward: |from: &Model, to: &mut Model| {
-      // get_first_block_where!(name='spacecraft_dry_mass').value as Mass.g -> Spacecraft.dryMass
-      let filter = HashMap::from([("name".to_string(), Value::String("battery_esr".into()))]);
-      let battery_esr_name = from.get_first_block_where(&filter).expect("Block matching filter expression not found.");
-      let esr = battery_esr_name.get("value").unwrap().as_f64().unwrap();
-      to.block_by_id_mut("NT0USZZSc9cZAmWJbClN-").expect("Block not found").insert("esr".to_string(), esr.into());
-      Ok(())
-    },
-    reverse: |from: &Model, to: &mut Model| {
-      // Spacecraft.root.dryMass as Mass.kg -> get_first_block_where!(name='spacecraft_dry_mass').value
-      let block = from.block_by_id("NT0USZZSc9cZAmWJbClN-").expect("Block not found");
-      let esr = block.get("esr").unwrap().as_f64().unwrap();
-      
-      let filter = HashMap::from([("name".to_string(), Value::String("battery_esr".into()))]);
-      let battery_esr_name = to.get_first_block_where_mut(&filter).expect("Block matching filter expression not found.");
-      battery_esr_name.insert("value".to_string(), esr.into());
-      Ok(())
-    },
-  };
+  // get_first_block_where!(name='battery_esr').value <-> Battery.esr
+  let excel_to_sedaroml = FieldMapping::new(
+    FieldRef::new(BlockSelector::Name("battery_esr".into()), "value"),
+    FieldRef::new(BlockSelector::Id("NT0USZZSc9cZAmWJbClN-".into()), "esr"),
+  ).named("-").build().expect("Invalid mapping.");
 
-  let excel_to_cosim = Operation {
-    name: Some("cosim".into()),
-    forward: |from: &Model, to: &mut Model| {
-      let filter = HashMap::from([("name".to_string(), Value::String("attitude_x".into()))]);
-      let block = from.get_first_block_where(&filter).expect("Block matching filter expression not found.");
-      let x = block.get("value").unwrap().as_f64().unwrap();
-      let filter = HashMap::from([("name".to_string(), Value::String("attitude_y".into()))]);
-      let block = from.get_first_block_where(&filter).expect("Block matching filter expression not found.");
-      let y = block.get("value").unwrap().as_f64().unwrap();
-      let filter = HashMap::from([("name".to_string(), Value::String("attitude_z".into()))]);
-      let block = from.get_first_block_where(&filter).expect("Block matching filter expression not found.");
-      let z = block.get("value").unwrap().as_f64().unwrap();
-      let filter = HashMap::from([("name".to_string(), Value::String("attitude_w".into()))]);
-      let block = from.get_first_block_where(&filter).expect("Block matching filter expression not found.");
-      let w = block.get("value").unwrap().as_f64().unwrap();
-      to.root.insert("produced_value".to_string(), serde_json::json!([{"ndarray": vec![x, y, z, w]}]));
+  let named = |name: &str| BlockSelector::Where(HashMap::from([("name".to_string(), Value::String(name.into()))]));
+  let excel_to_cosim = Operation::new(
+    Some("cosim".into()),
+    move |from: &Model, to: &mut Model| {
+      let mut attitude = vec![];
+      for axis in ["x", "y", "z", "w"] {
+        attitude.push(FieldRef::new(named(&format!("attitude_{}", axis)), "value").get_f64(from)?);
+      }
+      to.root.insert("produced_value".to_string(), serde_json::json!([{"ndarray": attitude}]));
       Ok(())
     },
-    reverse: |from: &Model, to: &mut Model| {
-      let vector = from.root.get("consumed_value").unwrap().get(0).unwrap().get("ndarray").unwrap().as_array().unwrap();
-      let x = vector[0].as_f64().unwrap();
-      let y = vector[1].as_f64().unwrap();
-      let z = vector[2].as_f64().unwrap();
-
-      let filter = HashMap::from([("name".to_string(), Value::String("position_eci_x".into()))]);
-      let block = to.get_first_block_where_mut(&filter).expect("Block matching filter expression not found.");
-      block.insert("value".to_string(), x.into());
-      let filter = HashMap::from([("name".to_string(), Value::String("position_eci_y".into()))]);
-      let block = to.get_first_block_where_mut(&filter).expect("Block matching filter expression not found.");
-      block.insert("value".to_string(), y.into());
-      let filter = HashMap::from([("name".to_string(), Value::String("position_eci_z".into()))]);
-      let block = to.get_first_block_where_mut(&filter).expect("Block matching filter expression not found.");
-      block.insert("value".to_string(), z.into());
+    move |from: &Model, to: &mut Model| {
+      for (i, axis) in ["x", "y", "z"].iter().enumerate() {
+        let value = FieldRef::new(BlockSelector::Root, &format!("consumed_value.0.ndarray.{}", i)).get_f64(from)?;
+        FieldRef::new(named(&format!("position_eci_{}", axis)), "value").set(to, value.into())?;
+      }
       Ok(())
     },
-  };
+  );
 
-  let other = Operation {
-    name: Some("other".into()),
-    forward: |_, _| {
+  let other = Operation::new(
+    Some("other".into()),
+    |_, _| {
       Ok(())
     },
-    reverse: |_, _| {
+    |_, _| {
       Ok(())
     },
-  };
+  );
 
   let t = Translation {
     from: excel.clone(),
@@ -124,6 +98,33 @@ async fn main() {
   };
 
   // let exchange = Exchange::new(vec![translation_cosim]);
-  let exchange = Exchange::new(vec![translation_cosim, t, tt]);
+  let exchange = Exchange::new(vec![translation_cosim, t, tt]).unwrap_or_else(
+    |e| panic!("Failed to start exchange: {}", e)
+  );
   exchange.wait();
 }
+
+/// `modex diff <old.json> <new.json> [--plain | --markdown | --html]` prints the changes between two SedaroML files
+fn diff_command(args: &[String]) {
+  let usage = "Usage: modex diff <old.json> <new.json> [--plain | --markdown | --html]";
+  let (files, flags): (Vec<&String>, Vec<&String>) = args.iter().partition(|arg| !arg.starts_with("--"));
+  let format = match flags.iter().map(|flag| flag.as_str()).collect::<Vec<_>>()[..] {
+    [] => DiffFormat::Terminal,
+    ["--plain"] => DiffFormat::Plain,
+    ["--markdown"] => DiffFormat::Markdown,
+    ["--html"] => DiffFormat::Html,
+    _ => exit_with(usage),
+  };
+  let [old, new] = files[..] else { exit_with(usage) };
+  let read = |filename: &str| read_model(filename).unwrap_or_else(
+    |e| exit_with(&format!("Failed to read SedaroML: {}: {:?}", filename, e))
+  );
+  let (old, new) = (read(old), read(new));
+  println!("{}", DiffRenderer::new(format).with_model(&old).render(&old.diff(&new)));
+}
+
+/// Prints `message` to stderr and exits with the status for command line usage errors
+fn exit_with(message: &str) -> ! {
+  eprintln!("{}", message);
+  std::process::exit(2);
+}
@@ -0,0 +1,259 @@
+//! Path-level diffs that descend into arrays and objects, so that changing one element of a large array records just
+//! that element rather than two copies of the whole array.
+
+use indexmap::IndexMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::model::sedaroml::{Block, BlockDiff, Model, ModelDiff, ValueDiff};
+
+/// A change to the value at a path
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum PathChange {
+  Added(Value),
+  Removed(Value),
+  Updated(ValueDiff),
+}
+
+/// Changes to a block, keyed by JSON Pointer (RFC 6901) relative to the block, e.g. `/position/eci/1`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeepBlockDiff {
+  pub changes: IndexMap<String, PathChange>,
+}
+
+impl DeepBlockDiff {
+  pub fn new(old: &Block, new: &Block) -> DeepBlockDiff {
+    let mut changes = IndexMap::new();
+    diff_fields(
+      "",
+      old.iter().map(|(k, v)| (k.as_str(), v)),
+      |key| new.get(key),
+      new.iter().filter(|(k, _)| !old.contains_key(*k)).map(|(k, v)| (k.as_str(), v)),
+      &mut changes,
+    );
+    DeepBlockDiff { changes }
+  }
+  pub fn is_empty(&self) -> bool {
+    self.changes.is_empty()
+  }
+
+  /// The shallow diff of the block's top-level fields, given the block the diff was computed from
+  pub fn shallow(&self, old: &Block) -> BlockDiff {
+    let mut new = old.clone();
+    let mut fields = vec![];
+    for (pointer, change) in &self.changes {
+      let segments = split_pointer(pointer);
+      if !fields.contains(&segments[0]) {
+        fields.push(segments[0].clone());
+      }
+      set_path(&mut new, &segments, change);
+    }
+    let mut diff = BlockDiff { added_fields: IndexMap::new(), removed_fields: IndexMap::new(), updated_fields: IndexMap::new() };
+    for field in fields {
+      match (old.get(&field), new.get(&field)) {
+        (None, Some(new_value)) => { diff.added_fields.insert(field, new_value.clone()); },
+        (Some(old_value), None) => { diff.removed_fields.insert(field, old_value.clone()); },
+        (Some(old_value), Some(new_value)) if old_value != new_value => {
+          diff.updated_fields.insert(field, ValueDiff { old_value: old_value.clone(), new_value: new_value.clone() });
+        },
+        _ => {},
+      }
+    }
+    diff
+  }
+}
+
+/// A `ModelDiff` whose updated blocks record changes by path
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeepModelDiff {
+  pub added_blocks: IndexMap<String, Block>,
+  pub removed_blocks: IndexMap<String, Block>,
+  pub updated_blocks: IndexMap<String, DeepBlockDiff>,
+  pub root: DeepBlockDiff,
+}
+
+impl DeepModelDiff {
+  pub fn is_empty(&self) -> bool {
+    self.added_blocks.is_empty() && self.removed_blocks.is_empty() && self.updated_blocks.is_empty() && self.root.is_empty()
+  }
+
+  /// The equivalent `ModelDiff`, for consumers that need whole field values, given the Model the diff was computed from
+  pub fn shallow(&self, old: &Model) -> ModelDiff {
+    ModelDiff {
+      added_blocks: self.added_blocks.clone(),
+      removed_blocks: self.removed_blocks.clone(),
+      updated_blocks: self.updated_blocks.iter().filter_map(|(id, diff)| {
+        let old_block = old.blocks.get(id)?;
+        Some((id.clone(), diff.shallow(old_block)))
+      }).collect(),
+      root: self.root.shallow(&old.root),
+    }
+  }
+}
+
+impl Model {
+  /// Like `diff`, but descends into arrays and objects and records changes to updated blocks by path
+  pub fn deep_diff(&self, new: &Model) -> DeepModelDiff {
+    let mut diff = DeepModelDiff::default();
+    for (block_id, new_block) in new.blocks.iter() {
+      match self.blocks.get(block_id) {
+        Some(old_block) => {
+          let block_diff = DeepBlockDiff::new(old_block, new_block);
+          if !block_diff.is_empty() {
+            diff.updated_blocks.insert(block_id.clone(), block_diff);
+          }
+        },
+        None => { diff.added_blocks.insert(block_id.clone(), new_block.clone()); },
+      }
+    }
+    for (block_id, old_block) in self.blocks.iter() {
+      if !new.blocks.contains_key(block_id) {
+        diff.removed_blocks.insert(block_id.clone(), old_block.clone());
+      }
+    }
+    diff.root = DeepBlockDiff::new(&self.root, &new.root);
+    diff
+  }
+}
+
+/// Escapes a key for use as a JSON Pointer segment
+pub(crate) fn escape(key: &str) -> String {
+  key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Splits a JSON Pointer into its unescaped segments
+pub(crate) fn split_pointer(pointer: &str) -> Vec<String> {
+  pointer.split('/').skip(1).map(|segment| segment.replace("~1", "/").replace("~0", "~")).collect()
+}
+
+/// Records the changes between the fields of two objects: those in `old` (looked up in the new object with `get`),
+/// followed by those only in the new object
+fn diff_fields<'a>(
+  path: &str,
+  old: impl Iterator<Item = (&'a str, &'a Value)>,
+  get: impl Fn(&str) -> Option<&'a Value>,
+  added: impl Iterator<Item = (&'a str, &'a Value)>,
+  changes: &mut IndexMap<String, PathChange>,
+) {
+  for (key, old_value) in old {
+    let path = format!("{}/{}", path, escape(key));
+    match get(key) {
+      Some(new_value) => diff_values(&path, old_value, new_value, changes),
+      None => { changes.insert(path, PathChange::Removed(old_value.clone())); },
+    }
+  }
+  for (key, new_value) in added {
+    changes.insert(format!("{}/{}", path, escape(key)), PathChange::Added(new_value.clone()));
+  }
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut IndexMap<String, PathChange>) {
+  match (old, new) {
+    (Value::Object(old), Value::Object(new)) => diff_fields(
+      path,
+      old.iter().map(|(k, v)| (k.as_str(), v)),
+      |key| new.get(key),
+      new.iter().filter(|(k, _)| !old.contains_key(*k)).map(|(k, v)| (k.as_str(), v)),
+      changes,
+    ),
+    (Value::Array(old), Value::Array(new)) => {
+      for (i, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+        diff_values(&format!("{}/{}", path, i), old_value, new_value, changes);
+      }
+      for (i, new_value) in new.iter().enumerate().skip(old.len()) {
+        changes.insert(format!("{}/{}", path, i), PathChange::Added(new_value.clone()));
+      }
+      // Trailing elements are removed last first so that the changes can be applied in order
+      for (i, old_value) in old.iter().enumerate().skip(new.len()).rev() {
+        changes.insert(format!("{}/{}", path, i), PathChange::Removed(old_value.clone()));
+      }
+    },
+    _ if old != new => {
+      changes.insert(path.to_string(), PathChange::Updated(ValueDiff { old_value: old.clone(), new_value: new.clone() }));
+    },
+    _ => {},
+  }
+}
+
+/// Applies a change at a path within a block.  Changes whose parent doesn't exist are ignored.
+pub(crate) fn set_path(block: &mut Block, segments: &[String], change: &PathChange) {
+  let (first, rest) = segments.split_first().unwrap();
+  if rest.is_empty() {
+    match change {
+      PathChange::Removed(_) => { block.shift_remove(first); },
+      PathChange::Added(value) | PathChange::Updated(ValueDiff { new_value: value, .. }) => {
+        block.insert(first.clone(), value.clone());
+      },
+    }
+    return;
+  }
+  let Some(mut value) = block.get_mut(first) else { return };
+  let (last, parents) = rest.split_last().unwrap();
+  for segment in parents {
+    let child = match value {
+      Value::Array(array) => segment.parse::<usize>().ok().and_then(|i| array.get_mut(i)),
+      Value::Object(object) => object.get_mut(segment),
+      _ => None,
+    };
+    let Some(child) = child else { return };
+    value = child;
+  }
+  match (value, change) {
+    (Value::Array(array), PathChange::Removed(_)) => {
+      if let Some(i) = last.parse::<usize>().ok().filter(|i| *i < array.len()) {
+        array.remove(i);
+      }
+    },
+    (Value::Array(array), PathChange::Added(new_value)) => {
+      if let Some(i) = last.parse::<usize>().ok().filter(|i| *i <= array.len()) {
+        array.insert(i, new_value.clone());
+      }
+    },
+    (Value::Array(array), PathChange::Updated(diff)) => {
+      if let Some(element) = last.parse::<usize>().ok().and_then(|i| array.get_mut(i)) {
+        *element = diff.new_value.clone();
+      }
+    },
+    (Value::Object(object), PathChange::Removed(_)) => { object.shift_remove(last); },
+    (Value::Object(object), PathChange::Added(new_value) | PathChange::Updated(ValueDiff { new_value, .. })) => {
+      object.insert(last.clone(), new_value.clone());
+    },
+    _ => {},
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use crate::model::sedaroml::{Model, ValueDiff};
+  use super::PathChange;
+
+  #[test]
+  fn test_deep_diff() {
+    let ndarray = (0..4000).map(|i| json!(i as f64)).collect::<Vec<_>>();
+    let old: Model = serde_json::from_value(json!({
+      "blocks": {
+        "a": {"type": "T", "ndarray": ndarray, "state": {"mode": "on", "a/b": 1, "gains": [1, 2, 3]}, "x": 1},
+      },
+      "index": {"T": ["a"]},
+      "name": "m",
+    })).unwrap();
+    let mut new = old.clone();
+    let block = new.blocks.get_mut("a").unwrap();
+    block["ndarray"][17] = json!(-1.0);
+    block["state"]["a/b"] = json!(2);
+    block["state"]["gains"] = json!([1, 2]);
+    block["state"].as_object_mut().unwrap().insert("extra".into(), json!(true));
+    block.shift_remove("x");
+    new.root.insert("name".into(), json!("n"));
+
+    let diff = old.deep_diff(&new);
+    let changes = &diff.updated_blocks["a"].changes;
+    assert_eq!(changes.keys().collect::<Vec<_>>(), ["/ndarray/17", "/state/a~1b", "/state/gains/2", "/state/extra", "/x"]);
+    assert_eq!(changes["/ndarray/17"], PathChange::Updated(ValueDiff { old_value: json!(17.0), new_value: json!(-1.0) }));
+    assert_eq!(changes["/state/gains/2"], PathChange::Removed(json!(3)));
+    assert_eq!(diff.root.changes["/name"], PathChange::Updated(ValueDiff { old_value: json!("m"), new_value: json!("n") }));
+    assert!(old.deep_diff(&old).is_empty());
+
+    assert_eq!(diff.shallow(&old), old.diff(&new));
+  }
+}
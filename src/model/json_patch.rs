@@ -0,0 +1,224 @@
+//! Conversion of `ModelDiff`s to and from JSON Patch (RFC 6902) documents that target the serialized SedaroML layout,
+//! e.g. `/blocks/<id>/<field>`, `/index/<type>` and `/<field>` for fields of the root.
+
+use std::collections::HashMap;
+use std::fmt;
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use crate::model::deep_diff::{escape, split_pointer};
+use crate::model::patch::PatchMode;
+use crate::model::sedaroml::{Block, BlockDiff, Model, ModelDiff, ValueDiff};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonPatchError {
+  /// The patch isn't an array of operations, or an operation is missing its `op`, `path` or `value`
+  InvalidPatch(String),
+  /// `move` and `copy` can't be expressed as a `ModelDiff`
+  UnsupportedOperation(String),
+  /// A path below a top-level field, or a replacement of a whole block whose old value wasn't tested
+  UnsupportedPath(String),
+}
+
+impl fmt::Display for JsonPatchError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      JsonPatchError::InvalidPatch(message) => write!(f, "Invalid JSON Patch: {}", message),
+      JsonPatchError::UnsupportedOperation(op) => write!(f, "Unsupported JSON Patch operation `{}`", op),
+      JsonPatchError::UnsupportedPath(path) => write!(f, "Unsupported JSON Patch path `{}`", path),
+    }
+  }
+}
+
+impl std::error::Error for JsonPatchError {}
+
+/// Appends the operations for a block diff, each `remove` and `replace` preceded by a `test` of the value it expects
+fn block_operations(prefix: &str, diff: &BlockDiff, operations: &mut Vec<Value>) {
+  for (field, old_value) in &diff.removed_fields {
+    let path = format!("{}/{}", prefix, escape(field));
+    operations.push(json!({"op": "test", "path": path, "value": old_value}));
+    operations.push(json!({"op": "remove", "path": path}));
+  }
+  for (field, value_diff) in &diff.updated_fields {
+    let path = format!("{}/{}", prefix, escape(field));
+    operations.push(json!({"op": "test", "path": path, "value": value_diff.old_value}));
+    operations.push(json!({"op": "replace", "path": path, "value": value_diff.new_value}));
+  }
+  for (field, value) in &diff.added_fields {
+    operations.push(json!({"op": "add", "path": format!("{}/{}", prefix, escape(field)), "value": value}));
+  }
+}
+
+impl ModelDiff {
+  /// The diff as a JSON Patch, given the Model the diff was computed from.  The patch also updates the lists in the
+  /// `index` that the diff's added, removed and retyped blocks change, which the Model is needed to locate.  Every
+  /// `remove` and `replace` is preceded by a `test` of the value it expects, so applying the patch fails on a document
+  /// that has drifted and `from_json_patch` can recover the diff's old values.
+  pub fn to_json_patch(&self, old: &Model) -> Value {
+    let mut operations = vec![];
+    for (id, block) in &self.removed_blocks {
+      let path = format!("/blocks/{}", escape(id));
+      operations.push(json!({"op": "test", "path": path, "value": block}));
+      operations.push(json!({"op": "remove", "path": path}));
+    }
+    for (id, block_diff) in &self.updated_blocks {
+      block_operations(&format!("/blocks/{}", escape(id)), block_diff, &mut operations);
+    }
+    for (id, block) in &self.added_blocks {
+      operations.push(json!({"op": "add", "path": format!("/blocks/{}", escape(id)), "value": block}));
+    }
+    block_operations("", &self.root, &mut operations);
+
+    let mut new = old.clone();
+    if new.apply(self, PatchMode::Lenient).is_ok() {
+      for (block_type, entries) in &old.index {
+        let path = format!("/index/{}", escape(block_type));
+        match new.index.get(block_type) {
+          Some(new_entries) if new_entries == entries => {},
+          Some(new_entries) => {
+            operations.push(json!({"op": "test", "path": path, "value": entries}));
+            operations.push(json!({"op": "replace", "path": path, "value": new_entries}));
+          },
+          None => {
+            operations.push(json!({"op": "test", "path": path, "value": entries}));
+            operations.push(json!({"op": "remove", "path": path}));
+          },
+        }
+      }
+      for (block_type, entries) in new.index.iter().filter(|(t, _)| !old.index.contains_key(*t)) {
+        operations.push(json!({"op": "add", "path": format!("/index/{}", escape(block_type)), "value": entries}));
+      }
+    }
+    Value::Array(operations)
+  }
+
+  /// Reads a JSON Patch produced by `to_json_patch` or by another tool.  The old value of a removed or replaced field
+  /// or block is taken from a preceding `test` of its path, and is `null` (or an empty block) if there is none, so a
+  /// diff read from a patch without tests should be applied with `PatchMode::Lenient`.  Operations on the `index` are
+  /// skipped because `Model::apply` maintains it.
+  pub fn from_json_patch(patch: &Value) -> Result<ModelDiff, JsonPatchError> {
+    let operations = patch.as_array().ok_or_else(|| JsonPatchError::InvalidPatch("Expected an array of operations".into()))?;
+    let mut diff = ModelDiff {
+      added_blocks: IndexMap::new(),
+      removed_blocks: IndexMap::new(),
+      updated_blocks: IndexMap::new(),
+      root: BlockDiff { added_fields: IndexMap::new(), removed_fields: IndexMap::new(), updated_fields: IndexMap::new() },
+    };
+    let mut tested: HashMap<&str, &Value> = HashMap::new();
+    for operation in operations {
+      let field = |name: &str| operation.get(name).ok_or_else(
+        || JsonPatchError::InvalidPatch(format!("Operation without `{}`: {}", name, operation))
+      );
+      let op = field("op")?.as_str().ok_or_else(|| JsonPatchError::InvalidPatch(format!("Invalid `op`: {}", operation)))?;
+      let path = field("path")?.as_str().ok_or_else(|| JsonPatchError::InvalidPatch(format!("Invalid `path`: {}", operation)))?;
+      if op == "test" {
+        tested.insert(path, field("value")?);
+        continue;
+      }
+      let segments = split_pointer(path);
+      let (block_id, field_name) = match segments.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["index", ..] => continue,
+        ["blocks", id] => (Some(id), None),
+        ["blocks", id, field] => (Some(id), Some(field)),
+        [field] if field != "blocks" => (None, Some(field)),
+        _ => return Err(JsonPatchError::UnsupportedPath(path.to_string())),
+      };
+      let old_value = tested.get(path).copied();
+      match (op, block_id, field_name) {
+        ("add", Some(id), None) => {
+          let block = serde_json::from_value::<Block>(field("value")?.clone())
+            .map_err(|e| JsonPatchError::InvalidPatch(format!("Block `{}` is not an object: {}", id, e)))?;
+          diff.added_blocks.insert(id.to_string(), block);
+        },
+        ("remove", Some(id), None) => {
+          let block = old_value.and_then(|v| serde_json::from_value::<Block>(v.clone()).ok()).unwrap_or_default();
+          diff.removed_blocks.insert(id.to_string(), block);
+        },
+        ("replace", Some(id), None) => {
+          let old_block = old_value.and_then(|v| serde_json::from_value::<Block>(v.clone()).ok())
+            .ok_or_else(|| JsonPatchError::UnsupportedPath(path.to_string()))?;
+          let new_block = serde_json::from_value::<Block>(field("value")?.clone())
+            .map_err(|e| JsonPatchError::InvalidPatch(format!("Block `{}` is not an object: {}", id, e)))?;
+          diff.updated_blocks.insert(id.to_string(), BlockDiff::new(&old_block, &new_block));
+        },
+        (op @ ("add" | "remove" | "replace"), block_id, Some(field_name)) => {
+          let block_diff = match block_id {
+            Some(id) => diff.updated_blocks.entry(id.to_string()).or_insert_with(|| BlockDiff {
+              added_fields: IndexMap::new(), removed_fields: IndexMap::new(), updated_fields: IndexMap::new(),
+            }),
+            None => &mut diff.root,
+          };
+          let old_value = old_value.cloned().unwrap_or(Value::Null);
+          match op {
+            "add" => { block_diff.added_fields.insert(field_name.to_string(), field("value")?.clone()); },
+            "remove" => { block_diff.removed_fields.insert(field_name.to_string(), old_value); },
+            _ => {
+              let new_value = field("value")?.clone();
+              block_diff.updated_fields.insert(field_name.to_string(), ValueDiff { old_value, new_value });
+            },
+          }
+        },
+        (op, _, _) => return Err(JsonPatchError::UnsupportedOperation(op.to_string())),
+      }
+    }
+    Ok(diff)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use crate::model::sedaroml::{Model, ModelDiff};
+  use super::JsonPatchError;
+
+  #[test]
+  fn test_json_patch() {
+    let old: Model = serde_json::from_value(json!({
+      "blocks": {
+        "a": {"type": "T", "x": 1, "y": 1},
+        "b": {"type": "T"},
+      },
+      "index": {"T": ["a", "b"]},
+      "name": "m",
+    })).unwrap();
+    let mut new = old.clone();
+    new.remove_block("b", false).unwrap();
+    new.add_block("c/d", serde_json::from_value(json!({"type": "U"})).unwrap()).unwrap();
+    let a = new.blocks.get_mut("a").unwrap();
+    a.insert("x".into(), json!(2));
+    a.shift_remove("y");
+    a.insert("z".into(), json!([1]));
+    new.root.insert("name".into(), json!("n"));
+
+    let diff = old.diff(&new);
+    let patch = diff.to_json_patch(&old);
+    assert_eq!(patch, json!([
+      {"op": "test", "path": "/blocks/b", "value": {"type": "T"}},
+      {"op": "remove", "path": "/blocks/b"},
+      {"op": "test", "path": "/blocks/a/y", "value": 1},
+      {"op": "remove", "path": "/blocks/a/y"},
+      {"op": "test", "path": "/blocks/a/x", "value": 1},
+      {"op": "replace", "path": "/blocks/a/x", "value": 2},
+      {"op": "add", "path": "/blocks/a/z", "value": [1]},
+      {"op": "add", "path": "/blocks/c~1d", "value": {"type": "U"}},
+      {"op": "test", "path": "/name", "value": "m"},
+      {"op": "replace", "path": "/name", "value": "n"},
+      {"op": "test", "path": "/index/T", "value": ["a", "b"]},
+      {"op": "replace", "path": "/index/T", "value": ["a"]},
+      {"op": "add", "path": "/index/U", "value": ["c/d"]},
+    ]));
+    assert_eq!(ModelDiff::from_json_patch(&patch).unwrap(), diff);
+
+    let without_tests = json!([{"op": "replace", "path": "/blocks/a/x", "value": 3}]);
+    let imported = ModelDiff::from_json_patch(&without_tests).unwrap();
+    assert_eq!(imported.updated_blocks["a"].updated_fields["x"].old_value, json!(null));
+    assert_eq!(
+      ModelDiff::from_json_patch(&json!([{"op": "move", "from": "/a", "path": "/b"}])),
+      Err(JsonPatchError::UnsupportedOperation("move".into())),
+    );
+    assert_eq!(
+      ModelDiff::from_json_patch(&json!([{"op": "add", "path": "/blocks/a/z/0", "value": 1}])),
+      Err(JsonPatchError::UnsupportedPath("/blocks/a/z/0".into())),
+    );
+    assert!(matches!(ModelDiff::from_json_patch(&json!({})), Err(JsonPatchError::InvalidPatch(_))));
+  }
+}
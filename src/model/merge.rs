@@ -0,0 +1,200 @@
+//! Three-way merges of models that have both changed since a common base version.
+
+use std::fmt;
+use indexmap::IndexMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::model::sedaroml::{Block, Model};
+
+/// One of the two models being merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+  Ours,
+  Theirs,
+}
+
+/// A change made by both sides that can't be merged automatically
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeConflict {
+  /// Both sides changed a field of a block (`None` for the root) to different values.  `None` values are absent.
+  Field { block: Option<String>, field: String, base: Option<Box<Value>>, ours: Option<Box<Value>>, theirs: Option<Box<Value>> },
+  /// One side removed a block that the other changed
+  Removal { block: String, removed_by: Side },
+}
+
+impl fmt::Display for MergeConflict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let show = |value: &Option<Box<Value>>| value.as_ref().map_or("<absent>".to_string(), |v| v.to_string());
+    match self {
+      MergeConflict::Field { block: Some(block), field, ours, theirs, .. } => {
+        write!(f, "Field `{}` of block `{}`: ours {}, theirs {}", field, block, show(ours), show(theirs))
+      },
+      MergeConflict::Field { block: None, field, ours, theirs, .. } => {
+        write!(f, "Root field `{}`: ours {}, theirs {}", field, show(ours), show(theirs))
+      },
+      MergeConflict::Removal { block, removed_by: Side::Ours } => write!(f, "Block `{}` was removed by ours and changed by theirs", block),
+      MergeConflict::Removal { block, removed_by: Side::Theirs } => write!(f, "Block `{}` was changed by ours and removed by theirs", block),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+  /// The merged model.  Conflicting fields and blocks are left as they are in `ours`.
+  pub merged: Model,
+  pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+  pub fn is_clean(&self) -> bool {
+    self.conflicts.is_empty()
+  }
+}
+
+/// Merges the values of one field or block, returning `None` if both sides changed it differently
+fn merge_value<'a, T: PartialEq>(base: Option<&'a T>, ours: Option<&'a T>, theirs: Option<&'a T>) -> Option<Option<&'a T>> {
+  if ours == theirs || theirs == base {
+    Some(ours)
+  } else if ours == base {
+    Some(theirs)
+  } else {
+    None
+  }
+}
+
+/// Merges the fields of a block, in the order of `ours` followed by fields only `theirs` has
+fn merge_fields(id: Option<&str>, base: &Block, ours: &Block, theirs: &Block, conflicts: &mut Vec<MergeConflict>) -> Block {
+  let mut merged = Block::new();
+  let fields = ours.keys().chain(theirs.keys().filter(|k| !ours.contains_key(*k)))
+    .chain(base.keys().filter(|k| !ours.contains_key(*k) && !theirs.contains_key(*k)));
+  for field in fields {
+    let (b, o, t) = (base.get(field), ours.get(field), theirs.get(field));
+    let value = merge_value(b, o, t).unwrap_or_else(|| {
+      conflicts.push(MergeConflict::Field {
+        block: id.map(str::to_string), field: field.clone(),
+        base: b.cloned().map(Box::new), ours: o.cloned().map(Box::new), theirs: t.cloned().map(Box::new),
+      });
+      o
+    });
+    if let Some(value) = value {
+      merged.insert(field.clone(), value.clone());
+    }
+  }
+  merged
+}
+
+/// Merges the entries listed under each type of an index, in the order of `ours` followed by entries `theirs` added
+fn merge_index(
+  base: &IndexMap<String, Vec<String>>, ours: &IndexMap<String, Vec<String>>, theirs: &IndexMap<String, Vec<String>>,
+) -> IndexMap<String, Vec<String>> {
+  let empty = vec![];
+  let mut merged = IndexMap::new();
+  for block_type in ours.keys().chain(theirs.keys().filter(|k| !ours.contains_key(*k))) {
+    let b = base.get(block_type).unwrap_or(&empty);
+    let o = ours.get(block_type).unwrap_or(&empty);
+    let t = theirs.get(block_type).unwrap_or(&empty);
+    let mut entries: Vec<String> = o.iter().filter(|e| !b.contains(e) || t.contains(e)).cloned().collect();
+    entries.extend(t.iter().filter(|e| !b.contains(e) && !o.contains(e)).cloned());
+    if !entries.is_empty() || (ours.contains_key(block_type) && theirs.contains_key(block_type)) {
+      merged.insert(block_type.clone(), entries);
+    }
+  }
+  merged
+}
+
+impl Model {
+  /// Merges the changes that `ours` and `theirs` each made to `base`.  Changes to different blocks or fields are
+  /// combined; a field changed differently by both sides, or a block removed by one side and changed by the other, is
+  /// a conflict and is left as it is in `ours`.  Metadata is taken from whichever side changed it, preferring `ours`.
+  pub fn merge3(base: &Model, ours: &Model, theirs: &Model) -> MergeResult {
+    let mut conflicts = vec![];
+    let mut merged = Model::new();
+    let ids = ours.blocks.keys().chain(theirs.blocks.keys().filter(|k| !ours.blocks.contains_key(*k)));
+    for id in ids {
+      let (b, o, t) = (base.blocks.get(id), ours.blocks.get(id), theirs.blocks.get(id));
+      let block = match (merge_value(b, o, t), o, t) {
+        (Some(block), _, _) => block.cloned(),
+        (None, Some(o), Some(t)) => Some(merge_fields(Some(id), b.unwrap_or(&Block::new()), o, t, &mut conflicts)),
+        (None, o, _) => {
+          let removed_by = if o.is_none() { Side::Ours } else { Side::Theirs };
+          conflicts.push(MergeConflict::Removal { block: id.clone(), removed_by });
+          o.cloned()
+        },
+      };
+      if let Some(block) = block {
+        merged.blocks.insert(id.clone(), block);
+      }
+    }
+    merged.root = merge_fields(None, &base.root, &ours.root, &theirs.root, &mut conflicts);
+    merged.metadata = if ours.metadata != base.metadata { ours.metadata.clone() } else { theirs.metadata.clone() };
+
+    // Drop index entries for blocks that didn't survive the merge and index blocks that the merge of the index missed
+    let was_block = |entry: &String| [base, ours, theirs].iter().any(|model| model.blocks.contains_key(entry));
+    merged.index = merge_index(&base.index, &ours.index, &theirs.index);
+    for entries in merged.index.values_mut() {
+      entries.retain(|entry| merged.blocks.contains_key(entry) || !was_block(entry));
+    }
+    for (id, block) in &merged.blocks {
+      let Some(block_type) = block.get("type").and_then(|t| t.as_str()) else { continue };
+      let entries = merged.index.entry(block_type.to_string()).or_default();
+      if !entries.contains(id) {
+        entries.push(id.clone());
+      }
+    }
+    MergeResult { merged, conflicts }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use crate::model::sedaroml::{Block, Model};
+  use super::{MergeConflict, Side};
+
+  #[test]
+  fn test_merge3() {
+    let base: Model = serde_json::from_value(json!({
+      "blocks": {
+        "a": {"type": "T", "x": 1, "y": 1, "z": 1},
+        "b": {"type": "T", "x": 1},
+        "c": {"type": "T", "x": 1},
+      },
+      "index": {"T": ["a", "b", "c"]},
+      "name": "m",
+    })).unwrap();
+    let mut ours = base.clone();
+    let mut theirs = base.clone();
+
+    ours.blocks.get_mut("a").unwrap().insert("x".into(), json!(2));
+    ours.blocks.get_mut("a").unwrap().insert("z".into(), json!(2));
+    theirs.blocks.get_mut("a").unwrap().insert("y".into(), json!(3));
+    theirs.blocks.get_mut("a").unwrap().insert("z".into(), json!(3));
+    ours.remove_block("b", false).unwrap();
+    theirs.blocks.get_mut("b").unwrap().insert("x".into(), json!(2));
+    theirs.remove_block("c", false).unwrap();
+    ours.add_block("d", serde_json::from_value(json!({"type": "T"})).unwrap()).unwrap();
+    theirs.add_block("e", serde_json::from_value(json!({"type": "U"})).unwrap()).unwrap();
+    theirs.root.insert("name".into(), json!("n"));
+
+    let result = Model::merge3(&base, &ours, &theirs);
+    assert_eq!(result.conflicts, vec![
+      MergeConflict::Field {
+        block: Some("a".into()), field: "z".into(),
+        base: Some(Box::new(json!(1))), ours: Some(Box::new(json!(2))), theirs: Some(Box::new(json!(3))),
+      },
+      MergeConflict::Removal { block: "b".into(), removed_by: Side::Ours },
+    ]);
+    let merged = result.merged;
+    assert_eq!(merged.blocks["a"], serde_json::from_value::<Block>(json!({"type": "T", "x": 2, "y": 3, "z": 2})).unwrap());
+    assert_eq!(merged.blocks.keys().collect::<Vec<_>>(), ["a", "d", "e"]);
+    assert_eq!(merged.index["T"], ["a", "d"]);
+    assert_eq!(merged.index["U"], ["e"]);
+    assert_eq!(merged.root["name"], json!("n"));
+    assert!(merged.validate().is_empty());
+
+    let clean = Model::merge3(&base, &base, &theirs);
+    assert!(clean.is_clean());
+    assert!(clean.merged.diff(&theirs).is_empty());
+    assert_eq!(clean.merged.index, theirs.index);
+  }
+}
@@ -1,2 +1,9 @@
 mod temp;
+pub mod deep_diff;
+pub mod json_patch;
+pub mod merge;
+pub mod patch;
+pub mod render;
 pub mod sedaroml;
+pub mod structure;
+pub mod units;
@@ -0,0 +1,306 @@
+//! Applying, inverting and composing `ModelDiff`s, so that a diff can be replayed onto a Model or undone.
+
+use std::fmt;
+use indexmap::IndexMap;
+use serde_json::Value;
+use crate::model::sedaroml::{Block, BlockDiff, Model, ModelDiff, ValueDiff};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PatchMode {
+  /// Fail without changing the Model unless it is exactly in the state the diff was computed from
+  #[default]
+  Strict,
+  /// Apply every change that can be applied, overwriting whatever the Model holds and skipping updates to missing blocks
+  Lenient,
+}
+
+/// Where a Model disagrees with the state a diff expects.  `block` is `root` for the root of the Model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+  BlockExists(String),
+  BlockNotFound(String),
+  /// A removed block doesn't hold the content the diff recorded
+  BlockMismatch(String),
+  FieldExists { block: String, field: String },
+  FieldNotFound { block: String, field: String },
+  ValueMismatch { block: String, field: String, expected: Box<Value>, found: Box<Value> },
+}
+
+impl fmt::Display for PatchError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PatchError::BlockExists(id) => write!(f, "Block `{}` already exists", id),
+      PatchError::BlockNotFound(id) => write!(f, "Block `{}` not found", id),
+      PatchError::BlockMismatch(id) => write!(f, "Block `{}` doesn't match the removed block", id),
+      PatchError::FieldExists { block, field } => write!(f, "Field `{}` of block `{}` already exists", field, block),
+      PatchError::FieldNotFound { block, field } => write!(f, "Block `{}` has no field `{}`", block, field),
+      PatchError::ValueMismatch { block, field, expected, found } => {
+        write!(f, "Field `{}` of block `{}` is {} but {} was expected", field, block, found, expected)
+      },
+    }
+  }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Applies a block diff to a block
+fn patch_block(name: &str, block: &mut Block, diff: &BlockDiff, mode: PatchMode) -> Result<(), PatchError> {
+  let strict = mode == PatchMode::Strict;
+  for (field, old_value) in &diff.removed_fields {
+    match block.shift_remove(field) {
+      Some(found) if strict && found != *old_value => {
+        return Err(PatchError::ValueMismatch { block: name.into(), field: field.clone(), expected: Box::new(old_value.clone()), found: Box::new(found) });
+      },
+      None if strict => return Err(PatchError::FieldNotFound { block: name.into(), field: field.clone() }),
+      _ => {},
+    }
+  }
+  for (field, value_diff) in &diff.updated_fields {
+    match block.get_mut(field) {
+      Some(found) => {
+        if strict && *found != value_diff.old_value {
+          return Err(PatchError::ValueMismatch {
+            block: name.into(), field: field.clone(), expected: Box::new(value_diff.old_value.clone()), found: Box::new(found.clone()),
+          });
+        }
+        *found = value_diff.new_value.clone();
+      },
+      None if strict => return Err(PatchError::FieldNotFound { block: name.into(), field: field.clone() }),
+      None => { block.insert(field.clone(), value_diff.new_value.clone()); },
+    }
+  }
+  for (field, value) in &diff.added_fields {
+    if block.insert(field.clone(), value.clone()).is_some() && strict {
+      return Err(PatchError::FieldExists { block: name.into(), field: field.clone() });
+    }
+  }
+  Ok(())
+}
+
+impl Model {
+  /// Applies a diff computed by `Model::diff`.  Blocks that are added or removed are also added to or removed from the
+  /// `index` under their `type`.  In strict mode the Model is left unchanged if the diff doesn't apply cleanly.
+  pub fn apply(&mut self, diff: &ModelDiff, mode: PatchMode) -> Result<(), PatchError> {
+    let strict = mode == PatchMode::Strict;
+    let mut patched = self.clone();
+    for (id, block) in &diff.removed_blocks {
+      match patched.blocks.get(id) {
+        Some(found) if strict && found != block => return Err(PatchError::BlockMismatch(id.clone())),
+        Some(_) => { patched.remove_block(id, false).unwrap(); },
+        None if strict => return Err(PatchError::BlockNotFound(id.clone())),
+        None => {},
+      }
+    }
+    for (id, block_diff) in &diff.updated_blocks {
+      match patched.blocks.get_mut(id) {
+        Some(block) => {
+          let old_type = block.get("type").cloned();
+          patch_block(id, block, block_diff, mode)?;
+          // Move retyped blocks within the index
+          if let (Some(Value::String(old_type)), Some(Value::String(new_type))) = (old_type, block.get("type").cloned()) {
+            if old_type != new_type {
+              block.insert("type".to_string(), Value::String(old_type));
+              patched.retype_block(id, &new_type).unwrap();
+            }
+          }
+        },
+        None if strict => return Err(PatchError::BlockNotFound(id.clone())),
+        None => {},
+      }
+    }
+    for (id, block) in &diff.added_blocks {
+      if patched.blocks.contains_key(id) {
+        if strict {
+          return Err(PatchError::BlockExists(id.clone()));
+        }
+        patched.remove_block(id, false).unwrap();
+      }
+      if patched.add_block(id, block.clone()).is_err() {
+        // Untyped blocks (or blocks whose `id` field disagrees) aren't indexed
+        patched.blocks.insert(id.clone(), block.clone());
+      }
+    }
+    patch_block("root", &mut patched.root, &diff.root, mode)?;
+    *self = patched;
+    Ok(())
+  }
+}
+
+impl BlockDiff {
+  /// The diff that undoes this one
+  pub fn invert(&self) -> BlockDiff {
+    BlockDiff {
+      added_fields: self.removed_fields.clone(),
+      removed_fields: self.added_fields.clone(),
+      updated_fields: self.updated_fields.iter().map(|(field, d)| {
+        (field.clone(), ValueDiff { old_value: d.new_value.clone(), new_value: d.old_value.clone() })
+      }).collect(),
+    }
+  }
+
+  /// The diff equivalent to applying this diff and then `next`
+  pub fn compose(&self, next: &BlockDiff) -> BlockDiff {
+    // Value of each touched field before and after, where `None` means the field is absent
+    let mut changes: IndexMap<&String, (Option<&Value>, Option<&Value>)> = IndexMap::new();
+    for (diff, first) in [(self, true), (next, false)] {
+      let fields = diff.removed_fields.iter().map(|(f, v)| (f, Some(v), None))
+        .chain(diff.updated_fields.iter().map(|(f, d)| (f, Some(&d.old_value), Some(&d.new_value))))
+        .chain(diff.added_fields.iter().map(|(f, v)| (f, None, Some(v))));
+      for (field, before, after) in fields {
+        match changes.get_mut(field) {
+          Some(change) if !first => change.1 = after,
+          _ => { changes.insert(field, (before, after)); },
+        }
+      }
+    }
+    let mut composed = BlockDiff { added_fields: IndexMap::new(), removed_fields: IndexMap::new(), updated_fields: IndexMap::new() };
+    for (field, change) in changes {
+      match change {
+        (None, Some(new_value)) => { composed.added_fields.insert(field.clone(), new_value.clone()); },
+        (Some(old_value), None) => { composed.removed_fields.insert(field.clone(), old_value.clone()); },
+        (Some(old_value), Some(new_value)) if old_value != new_value => {
+          composed.updated_fields.insert(field.clone(), ValueDiff { old_value: old_value.clone(), new_value: new_value.clone() });
+        },
+        _ => {},
+      }
+    }
+    composed
+  }
+}
+
+/// A block's state before and after a diff
+#[derive(Clone)]
+enum BlockChange {
+  Added(Block),
+  Removed(Block),
+  Updated(BlockDiff),
+}
+
+impl ModelDiff {
+  /// The diff that undoes this one
+  pub fn invert(&self) -> ModelDiff {
+    ModelDiff {
+      added_blocks: self.removed_blocks.clone(),
+      removed_blocks: self.added_blocks.clone(),
+      updated_blocks: self.updated_blocks.iter().map(|(id, d)| (id.clone(), d.invert())).collect(),
+      root: self.root.invert(),
+    }
+  }
+
+  fn changes(&self) -> IndexMap<String, BlockChange> {
+    let mut changes = IndexMap::new();
+    changes.extend(self.removed_blocks.iter().map(|(id, b)| (id.clone(), BlockChange::Removed(b.clone()))));
+    changes.extend(self.updated_blocks.iter().map(|(id, d)| (id.clone(), BlockChange::Updated(d.clone()))));
+    changes.extend(self.added_blocks.iter().map(|(id, b)| (id.clone(), BlockChange::Added(b.clone()))));
+    changes
+  }
+
+  /// The diff equivalent to applying this diff and then `next`, which must have been computed from the result of this
+  /// one.  Replaying a sequence of rounds' diffs composed together is the same as replaying them one by one.
+  pub fn compose(&self, next: &ModelDiff) -> ModelDiff {
+    let mut changes = self.changes();
+    for (id, second) in next.changes() {
+      let composed = match (changes.get(&id), second) {
+        (None, second) => Some(second),
+        (Some(BlockChange::Added(_)), BlockChange::Removed(_)) => None,
+        (Some(BlockChange::Added(block)), BlockChange::Updated(diff)) => {
+          let mut block = block.clone();
+          patch_block(&id, &mut block, &diff, PatchMode::Lenient).unwrap();
+          Some(BlockChange::Added(block))
+        },
+        (Some(BlockChange::Removed(old)), BlockChange::Added(new)) => Some(BlockChange::Updated(BlockDiff::new(old, &new))),
+        (Some(BlockChange::Updated(diff)), BlockChange::Removed(block)) => {
+          let mut block = block.clone();
+          patch_block(&id, &mut block, &diff.invert(), PatchMode::Lenient).unwrap();
+          Some(BlockChange::Removed(block))
+        },
+        (Some(BlockChange::Updated(first)), BlockChange::Updated(second)) => Some(BlockChange::Updated(first.compose(&second))),
+        // Sequences that can't follow each other, such as adding a block twice; the later change wins
+        (Some(_), second) => Some(second),
+      };
+      match composed {
+        Some(BlockChange::Updated(diff)) if diff.is_empty() => { changes.shift_remove(&id); },
+        Some(change) => { changes.insert(id, change); },
+        None => { changes.shift_remove(&id); },
+      }
+    }
+    let mut composed = ModelDiff {
+      added_blocks: IndexMap::new(),
+      removed_blocks: IndexMap::new(),
+      updated_blocks: IndexMap::new(),
+      root: self.root.compose(&next.root),
+    };
+    for (id, change) in changes {
+      match change {
+        BlockChange::Added(block) => { composed.added_blocks.insert(id, block); },
+        BlockChange::Removed(block) => { composed.removed_blocks.insert(id, block); },
+        BlockChange::Updated(diff) => { composed.updated_blocks.insert(id, diff); },
+      }
+    }
+    composed
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use crate::model::sedaroml::Model;
+  use super::{PatchError, PatchMode};
+
+  fn model(blocks: serde_json::Value, root: serde_json::Value) -> Model {
+    let mut document = json!({"blocks": blocks, "index": {}});
+    document.as_object_mut().unwrap().extend(root.as_object().unwrap().clone());
+    let mut model: Model = serde_json::from_value(document).unwrap();
+    for (id, block) in model.blocks.clone() {
+      model.index.entry(block["type"].as_str().unwrap().to_string()).or_default().push(id);
+    }
+    model
+  }
+
+  #[test]
+  fn test_apply_invert_compose() {
+    let a = model(json!({
+      "x": {"type": "T", "v": 1, "w": 2},
+      "y": {"type": "T", "v": 3},
+    }), json!({"name": "a", "epoch": 0}));
+    let b = model(json!({
+      "x": {"type": "U", "v": 10, "z": 5},
+      "n": {"type": "T", "v": 4},
+    }), json!({"name": "b", "mode": "on"}));
+    let c = model(json!({
+      "x": {"type": "U", "v": 1, "w": 2},
+      "n": {"type": "T", "v": 5},
+      "y": {"type": "T", "v": 3},
+    }), json!({"name": "c", "epoch": 0}));
+
+    let ab = a.diff(&b);
+    let mut patched = a.clone();
+    patched.apply(&ab, PatchMode::Strict).unwrap();
+    assert!(patched.diff(&b).is_empty());
+    assert_eq!(patched.index, b.index);
+    patched.apply(&ab.invert(), PatchMode::Strict).unwrap();
+    assert!(patched.diff(&a).is_empty());
+    assert_eq!(patched.index["T"], ["x", "y"]);
+
+    let bc = b.diff(&c);
+    let ac = ab.compose(&bc);
+    assert_eq!(ac, a.diff(&c));
+    assert!(ab.compose(&ab.invert()).is_empty());
+    let mut replayed = a.clone();
+    replayed.apply(&ac, PatchMode::Strict).unwrap();
+    assert!(replayed.diff(&c).is_empty());
+
+    // Strict application fails and leaves the model unchanged when it isn't in the state the diff expects
+    let mut edited = a.clone();
+    edited.blocks.get_mut("x").unwrap().insert("v".into(), json!(7));
+    assert_eq!(
+      edited.apply(&ab, PatchMode::Strict),
+      Err(PatchError::ValueMismatch { block: "x".into(), field: "v".into(), expected: Box::new(json!(1)), found: Box::new(json!(7)) }),
+    );
+    assert_eq!(edited.blocks["x"]["v"], json!(7));
+    assert!(matches!(b.clone().apply(&ab, PatchMode::Strict), Err(PatchError::BlockNotFound(_))));
+    edited.blocks.shift_remove("y");
+    edited.apply(&ab, PatchMode::Lenient).unwrap();
+    assert!(edited.diff(&b).is_empty());
+  }
+}
@@ -0,0 +1,267 @@
+//! Human-readable rendering of `ModelDiff`s for conflict prompts, logs and reports.
+
+use std::fmt;
+use colored::Colorize;
+use serde_json::Value;
+use crate::model::sedaroml::{Block, BlockDiff, Model, ModelDiff};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+  /// Unified diff lines, colored for a terminal
+  #[default]
+  Terminal,
+  /// Unified diff lines without color
+  Plain,
+  /// A `diff` code block per block, for Markdown reports
+  Markdown,
+  /// A `<pre>` per block with `added`/`removed` spans, for HTML reports
+  Html,
+}
+
+/// Renders `ModelDiff`s block by block, naming blocks by their `name` field where available
+#[derive(Debug, Clone, Copy)]
+pub struct DiffRenderer<'a> {
+  pub format: DiffFormat,
+  /// The Model the diff was computed from, used to name updated blocks whose `name` didn't change
+  pub model: Option<&'a Model>,
+  /// Arrays and objects with more elements than this are truncated
+  pub max_elements: usize,
+}
+
+/// The lines of one changed block, or of the root
+struct Section {
+  sign: char,
+  title: String,
+  lines: Vec<(char, String)>,
+}
+
+impl<'a> DiffRenderer<'a> {
+  pub fn new(format: DiffFormat) -> DiffRenderer<'a> {
+    DiffRenderer { format, model: None, max_elements: 8 }
+  }
+
+  pub fn with_model(mut self, model: &'a Model) -> DiffRenderer<'a> {
+    self.model = Some(model);
+    self
+  }
+
+  pub fn render(&self, diff: &ModelDiff) -> String {
+    let summary = summary(diff);
+    let sections = self.sections(diff);
+    match self.format {
+      DiffFormat::Terminal | DiffFormat::Plain => {
+        let color = self.format == DiffFormat::Terminal;
+        let mut out = if color { summary.bold().to_string() } else { summary };
+        for section in sections {
+          let header = format!("{} {}", section.sign, section.title);
+          out.push('\n');
+          out.push_str(&if color { colorize(section.sign, &header).bold().to_string() } else { header });
+          for (sign, line) in section.lines {
+            let line = format!("{}   {}", sign, line);
+            out.push('\n');
+            out.push_str(&if color { colorize(sign, &line).to_string() } else { line });
+          }
+        }
+        out
+      },
+      DiffFormat::Markdown => {
+        let mut out = format!("**{}**\n", summary);
+        for section in sections {
+          out.push_str(&format!("\n#### {} {}\n", section.sign, section.title));
+          if !section.lines.is_empty() {
+            out.push_str("```diff\n");
+            for (sign, line) in section.lines {
+              out.push_str(&format!("{} {}\n", sign, line));
+            }
+            out.push_str("```\n");
+          }
+        }
+        out
+      },
+      DiffFormat::Html => {
+        let mut out = format!("<div class=\"modex-diff\">\n<p><strong>{}</strong></p>\n", escape_html(&summary));
+        for section in sections {
+          out.push_str(&format!("<h4>{} {}</h4>\n", section.sign, escape_html(&section.title)));
+          if !section.lines.is_empty() {
+            out.push_str("<pre>");
+            for (sign, line) in section.lines {
+              let class = if sign == '+' { "added" } else { "removed" };
+              out.push_str(&format!("<span class=\"{}\">{} {}</span>\n", class, sign, escape_html(&line)));
+            }
+            out.push_str("</pre>\n");
+          }
+        }
+        out.push_str("</div>\n");
+        out
+      },
+    }
+  }
+
+  fn sections(&self, diff: &ModelDiff) -> Vec<Section> {
+    let mut sections = vec![];
+    for (id, block) in &diff.added_blocks {
+      let lines = block.iter().map(|(field, value)| ('+', self.field_line(field, value))).collect();
+      sections.push(Section { sign: '+', title: title(id, block, block.get("name")), lines });
+    }
+    for (id, block) in &diff.removed_blocks {
+      let lines = block.iter().map(|(field, value)| ('-', self.field_line(field, value))).collect();
+      sections.push(Section { sign: '-', title: title(id, block, block.get("name")), lines });
+    }
+    for (id, block_diff) in &diff.updated_blocks {
+      let old_block = self.model.and_then(|model| model.blocks.get(id));
+      let name = block_diff.updated_fields.get("name").map(|d| &d.new_value)
+        .or_else(|| block_diff.added_fields.get("name"))
+        .or_else(|| old_block.and_then(|block| block.get("name")));
+      let title = title(id, old_block.unwrap_or(&Block::new()), name);
+      sections.push(Section { sign: '~', title, lines: self.field_lines(block_diff) });
+    }
+    if !diff.root.is_empty() {
+      sections.push(Section { sign: '~', title: "root".to_string(), lines: self.field_lines(&diff.root) });
+    }
+    sections
+  }
+
+  fn field_lines(&self, diff: &BlockDiff) -> Vec<(char, String)> {
+    let mut lines = vec![];
+    for (field, value_diff) in &diff.updated_fields {
+      match (&value_diff.old_value, &value_diff.new_value) {
+        // Show both arrays from just before their first difference, so that it isn't truncated away
+        (Value::Array(old), Value::Array(new)) => {
+          let first = old.iter().zip(new).position(|(a, b)| a != b).unwrap_or(old.len().min(new.len()));
+          let start = if first < self.max_elements { 0 } else { first - 1 };
+          lines.push(('-', format!("{}: {}", field, self.array(old, start))));
+          lines.push(('+', format!("{}: {}", field, self.array(new, start))));
+        },
+        (old_value, new_value) => {
+          lines.push(('-', self.field_line(field, old_value)));
+          lines.push(('+', self.field_line(field, new_value)));
+        },
+      }
+    }
+    lines.extend(diff.removed_fields.iter().map(|(field, value)| ('-', self.field_line(field, value))));
+    lines.extend(diff.added_fields.iter().map(|(field, value)| ('+', self.field_line(field, value))));
+    lines
+  }
+
+  fn field_line(&self, field: &str, value: &Value) -> String {
+    format!("{}: {}", field, self.value(value))
+  }
+
+  /// The value as compact JSON, with long arrays and objects truncated
+  fn value(&self, value: &Value) -> String {
+    match value {
+      Value::Array(elements) => self.array(elements, 0),
+      Value::Object(fields) => {
+        let mut shown = fields.iter().take(self.max_elements)
+          .map(|(k, v)| format!("{}: {}", Value::from(k.as_str()), self.value(v))).collect::<Vec<_>>();
+        if fields.len() > self.max_elements {
+          shown.push(format!("… ({} more)", fields.len() - self.max_elements));
+        }
+        format!("{{{}}}", shown.join(", "))
+      },
+      _ => value.to_string(),
+    }
+  }
+
+  /// An array showing at most `max_elements` elements from `start`
+  fn array(&self, elements: &[Value], start: usize) -> String {
+    let start = start.min(elements.len());
+    let end = (start + self.max_elements).min(elements.len());
+    let mut shown = elements[start..end].iter().map(|v| self.value(v)).collect::<Vec<_>>();
+    if start > 0 {
+      shown.insert(0, format!("… ({} before)", start));
+    }
+    if end < elements.len() {
+      shown.push(format!("… ({} more)", elements.len() - end));
+    }
+    format!("[{}]", shown.join(", "))
+  }
+}
+
+fn title(id: &str, block: &Block, name: Option<&Value>) -> String {
+  let title = match name.and_then(|name| name.as_str()) {
+    Some(name) => format!("{} ({})", name, id),
+    None => id.to_string(),
+  };
+  match block.get("type").and_then(|t| t.as_str()) {
+    Some(block_type) => format!("{} [{}]", title, block_type),
+    None => title,
+  }
+}
+
+fn summary(diff: &ModelDiff) -> String {
+  let root_fields = diff.root.added_fields.len() + diff.root.removed_fields.len() + diff.root.updated_fields.len();
+  format!(
+    "{} added, {} removed, {} updated blocks; {} root fields changed",
+    diff.added_blocks.len(), diff.removed_blocks.len(), diff.updated_blocks.len(), root_fields,
+  )
+}
+
+fn colorize(sign: char, text: &str) -> colored::ColoredString {
+  match sign {
+    '+' => text.green(),
+    '-' => text.red(),
+    _ => text.yellow(),
+  }
+}
+
+fn escape_html(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The diff in `DiffFormat::Plain` without a Model for names
+impl fmt::Display for ModelDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", DiffRenderer::new(DiffFormat::Plain).render(self))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use crate::model::sedaroml::Model;
+  use super::{DiffFormat, DiffRenderer};
+
+  #[test]
+  fn test_render() {
+    let old: Model = serde_json::from_value(json!({
+      "blocks": {
+        "sc": {"type": "Spacecraft", "name": "Wildfire", "dryMass": 100, "ndarray": (0..20).collect::<Vec<_>>()},
+        "bp": {"type": "BatteryPack", "name": "Pack <1>"},
+      },
+      "index": {"Spacecraft": ["sc"], "BatteryPack": ["bp"]},
+      "name": "m",
+    })).unwrap();
+    let mut new = old.clone();
+    new.remove_block("bp", false).unwrap();
+    new.add_block("sp", serde_json::from_value(json!({"type": "SolarPanel", "area": 2})).unwrap()).unwrap();
+    let sc = new.blocks.get_mut("sc").unwrap();
+    sc.insert("dryMass".into(), json!(120));
+    sc.insert("ndarray".into(), json!((0..21).map(|i| if i == 12 { -1 } else { i }).collect::<Vec<_>>()));
+    let diff = old.diff(&new);
+
+    let renderer = DiffRenderer::new(DiffFormat::Plain).with_model(&old);
+    assert_eq!(renderer.render(&diff), [
+      "1 added, 1 removed, 1 updated blocks; 0 root fields changed",
+      "+ sp [SolarPanel]",
+      "+   type: \"SolarPanel\"",
+      "+   area: 2",
+      "- Pack <1> (bp) [BatteryPack]",
+      "-   type: \"BatteryPack\"",
+      "-   name: \"Pack <1>\"",
+      "~ Wildfire (sc) [Spacecraft]",
+      "-   dryMass: 100",
+      "+   dryMass: 120",
+      "-   ndarray: [… (11 before), 11, 12, 13, 14, 15, 16, 17, 18, … (1 more)]",
+      "+   ndarray: [… (11 before), 11, -1, 13, 14, 15, 16, 17, 18, … (2 more)]",
+    ].join("\n"));
+    assert!(diff.to_string().contains("~ sc\n-   dryMass: 100"));
+
+    let markdown = DiffRenderer::new(DiffFormat::Markdown).with_model(&old).render(&diff);
+    assert!(markdown.starts_with("**1 added, 1 removed, 1 updated blocks; 0 root fields changed**\n"));
+    assert!(markdown.contains("#### ~ Wildfire (sc) [Spacecraft]\n```diff\n- dryMass: 100\n+ dryMass: 120\n"));
+    let html = DiffRenderer::new(DiffFormat::Html).with_model(&old).render(&diff);
+    assert!(html.contains("<h4>- Pack &lt;1&gt; (bp) [BatteryPack]</h4>"));
+    assert!(html.contains("<span class=\"added\">+ dryMass: 120</span>"));
+  }
+}
@@ -5,6 +5,7 @@ use serde_json::Value;
 use indexmap::IndexMap;
 use crate::utils::{read_json, write_json};
 use super::temp::TempModel;
+use super::units::{Quantity, Unit, UnitError};
 
 pub type Block = IndexMap<String, Value>;
 
@@ -13,6 +14,19 @@ pub enum ModelError {
   BlockTypeNotFound(String),
   BlockNotFound(String),
   FileError(String),
+  FieldNotFound { block: String, field: String },
+  NotANumber { block: String, field: String },
+  /// A field doesn't hold a block ID, a list of block IDs or an object keyed by block ID
+  NotARelationship { block: String, field: String },
+  DuplicateBlock(String),
+  InvalidBlock(String),
+  UnitError(UnitError),
+}
+
+impl From<UnitError> for ModelError {
+  fn from(e: UnitError) -> Self {
+    ModelError::UnitError(e)
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -21,14 +35,135 @@ pub struct Model {
   pub blocks: IndexMap<String, Block>,
   pub index: IndexMap<String, Vec<String>>,
   #[serde(flatten)]
+  pub metadata: ModelMetadata,
+  #[serde(flatten)]
   pub root: Block,
 }
 
+/// Schema information that SedaroML carries alongside the blocks of a model.  It is written back out with the model but
+/// is not part of its content, so it is not compared by `Model::diff`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ModelMetadata {
+  /// Names of the block types in the model's schema
+  #[serde(rename = "_blockNames", default, skip_serializing_if = "Vec::is_empty")]
+  pub block_names: Vec<String>,
+  /// Quantity kind of each field, by block type then field (e.g. `Mass` or `Mass.g`)
+  #[serde(rename = "_quantityKinds", default, skip_serializing_if = "IndexMap::is_empty")]
+  pub quantity_kinds: IndexMap<String, IndexMap<String, String>>,
+  /// Relationship fields, by block type then field
+  #[serde(rename = "_relationships", default, skip_serializing_if = "IndexMap::is_empty")]
+  pub relationships: IndexMap<String, IndexMap<String, Relationship>>,
+  /// Direct supertypes of each block type
+  #[serde(rename = "_supers", default, skip_serializing_if = "IndexMap::is_empty")]
+  pub supers: IndexMap<String, Vec<String>>,
+  #[serde(rename = "_abstractBlockTypes", default, skip_serializing_if = "Vec::is_empty")]
+  pub abstract_block_types: Vec<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub migrated: Option<Value>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub issues: Option<Value>,
+  /// Relationship fields declared with `Model::declare_relationship`, by block type then field.  They are consulted
+  /// after `_relationships` and are not written out with the model.
+  #[serde(skip)]
+  pub declared_relationships: IndexMap<String, IndexMap<String, Relationship>>,
+}
+
+impl ModelMetadata {
+  /// The relationship declared for a field of a block type or of any of its supertypes
+  pub fn relationship(&self, block_type: &str, field: &str) -> Option<&Relationship> {
+    self.relationship_fields(block_type).into_iter().find(|(f, _)| *f == field).map(|(_, relationship)| relationship)
+  }
+  /// The relationship fields of a block type, including those inherited from its supertypes.  Fields in
+  /// `_relationships` take precedence over explicit declarations.
+  pub fn relationship_fields(&self, block_type: &str) -> Vec<(&str, &Relationship)> {
+    let mut fields: Vec<(&str, &Relationship)> = vec![];
+    let ancestors = self.ancestors(block_type);
+    for relationships in [&self.relationships, &self.declared_relationships] {
+      for block_type in &ancestors {
+        for (field, relationship) in relationships.get(*block_type).into_iter().flatten() {
+          if !fields.iter().any(|(f, _)| f == field) {
+            fields.push((field, relationship));
+          }
+        }
+      }
+    }
+    fields
+  }
+  /// A block type followed by all of its supertypes, nearest first
+  pub fn ancestors<'a>(&'a self, block_type: &'a str) -> Vec<&'a str> {
+    let mut ancestors = vec![block_type];
+    let mut i = 0;
+    while i < ancestors.len() {
+      for parent in self.supers(ancestors[i]) {
+        if !ancestors.contains(&parent.as_str()) {
+          ancestors.push(parent);
+        }
+      }
+      i += 1;
+    }
+    ancestors
+  }
+  pub fn supers(&self, block_type: &str) -> &[String] {
+    self.supers.get(block_type).map(|supers| supers.as_slice()).unwrap_or_default()
+  }
+  pub fn is_abstract(&self, block_type: &str) -> bool {
+    self.abstract_block_types.iter().any(|t| t == block_type)
+  }
+}
+
+/// A relationship field declared in `_relationships`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Relationship {
+  /// `OneSide` (a block ID), `ManySide` (a list of block IDs) or `DataSide` (an object keyed by block ID)
+  #[serde(rename = "type", default)]
+  pub kind: String,
+  /// Block types the relationship can reference
+  #[serde(default)]
+  pub types: Vec<String>,
+  /// Field of the referenced blocks that references back
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub inverse: Option<String>,
+  #[serde(flatten)]
+  pub other: IndexMap<String, Value>,
+}
+
+impl Relationship {
+  pub fn new(kind: &str, types: &[&str], inverse: Option<&str>) -> Relationship {
+    Relationship {
+      kind: kind.to_string(),
+      types: types.iter().map(|t| t.to_string()).collect(),
+      inverse: inverse.map(String::from),
+      other: IndexMap::new(),
+    }
+  }
+}
+
+/// A relationship field of a block that references another block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+  pub block: String,
+  pub field: String,
+  pub target: String,
+}
+
+/// IDs referenced by a relationship field's value: a block ID, a list of block IDs, an object keyed by block ID, or
+/// null.  Returns `None` for any other value.
+pub fn referenced_ids(value: &Value) -> Option<Vec<String>> {
+  match value {
+    Value::String(id) => Some(vec![id.clone()]),
+    Value::Array(ids) => ids.iter().map(|id| id.as_str().map(String::from)).collect(),
+    Value::Object(ids) => Some(ids.keys().cloned().collect()),
+    Value::Null => Some(vec![]),
+    _ => None,
+  }
+}
+
 impl Model {
   pub fn new() -> Model {
     Model {
       blocks: IndexMap::new(),
       index: IndexMap::new(),
+      metadata: ModelMetadata::default(),
       root: IndexMap::new(),
     }
   }
@@ -136,42 +271,171 @@ impl Model {
     self.get_first_block_where(&filter)
   }
 
+  /// The unit declared for a field of a block in the model's `_quantityKinds`, if any
+  pub fn declared_unit(&self, block: &Block, field: &str) -> Result<Option<Unit>, ModelError> {
+    let spec = block.get("type").and_then(|t| t.as_str()).and_then(|block_type| {
+      self.metadata.ancestors(block_type).into_iter()
+        .find_map(|t| self.metadata.quantity_kinds.get(t).and_then(|fields| fields.get(field)))
+    });
+    match spec {
+      Some(spec) => Ok(Some(Unit::from_spec(spec)?)),
+      None => Ok(None),
+    }
+  }
+
+  pub fn unit_of(&self, block_id: &str, field: &str) -> Result<Unit, ModelError> {
+    match self.declared_unit(self.block_by_id(block_id)?, field)? {
+      Some(unit) => Ok(unit),
+      None => Err(UnitError::NoQuantityKind { block: block_id.to_string(), field: field.to_string() }.into()),
+    }
+  }
+
+  pub fn get_quantity(&self, block_id: &str, field: &str) -> Result<Quantity, ModelError> {
+    let unit = self.unit_of(block_id, field)?;
+    match self.block_by_id(block_id)?.get(field) {
+      Some(value) => match value.as_f64() {
+        Some(value) => Ok(Quantity::new(value, unit)),
+        None => Err(ModelError::NotANumber { block: block_id.to_string(), field: field.to_string() }),
+      },
+      None => Err(ModelError::FieldNotFound { block: block_id.to_string(), field: field.to_string() }),
+    }
+  }
+
+  /// Sets a block's field to a quantity, converted to the unit of the field
+  pub fn set_quantity(&mut self, block_id: &str, field: &str, quantity: Quantity) -> Result<(), ModelError> {
+    let converted = quantity.to(&self.unit_of(block_id, field)?)?;
+    self.block_by_id_mut(block_id)?.insert(field.to_string(), Value::from(converted.value));
+    Ok(())
+  }
+
+  /// Whether a name is a block type, i.e. is listed in the `index`, `_supers` or `_blockNames`
+  pub(crate) fn is_type(&self, name: &str) -> bool {
+    self.index.contains_key(name) || self.metadata.supers.contains_key(name) || self.metadata.block_names.iter().any(|n| n == name)
+  }
+
+  /// A block type and all of its subtypes, from the `index` and `_supers`
+  fn subtypes<'a>(&'a self, block_type: &'a str) -> Vec<&'a str> {
+    let mut types = vec![block_type];
+    let mut i = 0;
+    while i < types.len() {
+      // Index entries that are neither blocks nor types, e.g. IDs of removed blocks, are ignored
+      let children = self.index.get(types[i]).into_iter().flatten()
+        .filter(|entry| !self.blocks.contains_key(*entry) && self.is_type(entry))
+        .chain(self.metadata.supers.iter().filter(|(_, supers)| supers.iter().any(|s| s == types[i])).map(|(t, _)| t));
+      let children = children.map(|t| t.as_str()).collect::<Vec<_>>();
+      for child in children {
+        if !types.contains(&child) {
+          types.push(child);
+        }
+      }
+      i += 1;
+    }
+    types
+  }
+
+  /// The non-abstract types that are a block type or one of its subtypes
+  pub fn concrete_types_of<'a>(&'a self, block_type: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    self.subtypes(block_type).into_iter().filter(|t| !self.metadata.is_abstract(t))
+  }
+
+  /// Whether a block is of a type or one of its subtypes, by its `type` field or its entry in the `index`
+  pub fn is_instance_of(&self, block_id: &str, block_type: &str) -> bool {
+    let types = self.subtypes(block_type);
+    let own_type = self.blocks.get(block_id).and_then(|block| block.get("type")).and_then(|t| t.as_str());
+    own_type.is_some_and(|t| types.contains(&t))
+      || types.iter().any(|t| self.index.get(*t).is_some_and(|entries| entries.iter().any(|entry| entry == block_id)))
+  }
+
+  /// Blocks of a type or any of its subtypes whose fields hold all of the given values, in model order
+  pub fn blocks_of_type_where<'a>(
+    &'a self, block_type: &'a str, filter: &'a HashMap<String, Value>,
+  ) -> impl Iterator<Item = (&'a String, &'a Block)> + 'a {
+    let types = self.subtypes(block_type);
+    self.blocks.iter().filter(move |(id, block)| {
+      let typed = block.get("type").and_then(|t| t.as_str()).is_some_and(|t| types.contains(&t))
+        || types.iter().any(|t| self.index.get(*t).is_some_and(|entries| entries.contains(id)));
+      typed && filter.iter().all(|(k, v)| block.get(k) == Some(v))
+    })
+  }
+
+  /// Declares a relationship field for a block type, as a fallback for models whose `_relationships` don't cover it.
+  /// Relationships declared by `_relationships` take precedence.  Declarations aren't written out with the model.
+  pub fn declare_relationship(&mut self, block_type: &str, field: &str, relationship: Relationship) {
+    self.metadata.declared_relationships.entry(block_type.to_string()).or_default().insert(field.to_string(), relationship);
+  }
+
+  /// IDs of the blocks referenced by a relationship field of a block.  A declared relationship that the block doesn't
+  /// set references nothing.  If the block's type declares any relationships, other fields aren't relationships;
+  /// otherwise any field holding block IDs is followed.
+  pub fn related_ids(&self, block_id: &str, field: &str) -> Result<Vec<String>, ModelError> {
+    let block = self.block_by_id(block_id)?;
+    let relationship_fields = block.get("type").and_then(|t| t.as_str())
+      .map(|block_type| self.metadata.relationship_fields(block_type)).unwrap_or_default();
+    let declared = relationship_fields.iter().any(|(f, _)| *f == field);
+    match block.get(field) {
+      Some(_) if !declared && !relationship_fields.is_empty() => {
+        Err(ModelError::NotARelationship { block: block_id.to_string(), field: field.to_string() })
+      },
+      Some(value) => referenced_ids(value).ok_or_else(
+        || ModelError::NotARelationship { block: block_id.to_string(), field: field.to_string() }
+      ),
+      None if declared => Ok(vec![]),
+      None => Err(ModelError::FieldNotFound { block: block_id.to_string(), field: field.to_string() }),
+    }
+  }
+
+  /// Follows a relationship field of a block to the blocks it references
+  pub fn related(&self, block_id: &str, field: &str) -> Result<Vec<(String, &Block)>, ModelError> {
+    self.related_ids(block_id, field)?.into_iter().map(|id| {
+      let block = self.block_by_id(&id)?;
+      Ok((id, block))
+    }).collect()
+  }
+
+  /// References from the declared relationship fields of each block
+  pub fn references(&self) -> Vec<Reference> {
+    let mut references = vec![];
+    for (id, block) in &self.blocks {
+      let Some(block_type) = block.get("type").and_then(|t| t.as_str()) else { continue };
+      for (field, _) in self.metadata.relationship_fields(block_type) {
+        for target in block.get(field).and_then(referenced_ids).unwrap_or_default() {
+          references.push(Reference { block: id.clone(), field: field.to_string(), target });
+        }
+      }
+    }
+    references
+  }
+
+  /// References to a block from the declared relationship fields of other blocks
+  pub fn referencing(&self, block_id: &str) -> Vec<Reference> {
+    self.references().into_iter().filter(|r| r.target == block_id).collect()
+  }
+
+  /// References to blocks that don't exist
+  pub fn dangling_references(&self) -> Vec<Reference> {
+    self.references().into_iter().filter(|r| !self.blocks.contains_key(&r.target)).collect()
+  }
+
   pub fn to_pretty_string(&self) -> String {
     serde_json::to_string_pretty(&self).unwrap()
   }
 
   pub fn diff(&self, new: &Model) -> ModelDiff {
+    self.diff_with(new, &DiffOptions::default())
+  }
+
+  /// Like `diff`, but ignoring the fields and numeric differences that `options` allows
+  pub fn diff_with(&self, new: &Model, options: &DiffOptions) -> ModelDiff {
     let mut added_blocks = IndexMap::new();
     let mut removed_blocks = IndexMap::new();
     let mut updated_blocks = IndexMap::new();
     for (block_id, new_block) in new.blocks.iter() {
       match self.blocks.get(block_id) {
         Some(old_block) => {
-          let mut added_fields = IndexMap::new();
-          let mut removed_fields = IndexMap::new();
-          let mut updated_fields = IndexMap::new();
-          for (field_key, field_value) in new_block.iter() {
-            match old_block.get(field_key) {
-              Some(old_value) => {
-                if old_value != field_value {
-                  updated_fields.insert(field_key.clone(), ValueDiff { old_value: old_value.clone(), new_value: field_value.clone() });
-                }
-              },
-              None => {
-                added_fields.insert(field_key.clone(), field_value.clone());
-              },
-            }
-          }
-          for (field_key, field_value) in old_block.iter() {
-            match new_block.get(field_key) {
-              Some(_) => {},
-              None => {
-                removed_fields.insert(field_key.clone(), field_value.clone());
-              },
-            }
-          }
-          if !added_fields.is_empty() || !removed_fields.is_empty() || !updated_fields.is_empty() {
-            updated_blocks.insert(block_id.clone(), BlockDiff { added_fields, removed_fields, updated_fields });
+          let block_type = new_block.get("type").and_then(|t| t.as_str());
+          let block_diff = BlockDiff::with_options(old_block, new_block, block_type, options);
+          if !block_diff.is_empty() {
+            updated_blocks.insert(block_id.clone(), block_diff);
           }
         },
         None => {
@@ -184,30 +448,8 @@ impl Model {
         removed_blocks.insert(block_id.clone(), old_block.clone());
       }
     }
-    let mut added_fields = IndexMap::new();
-    let mut removed_fields = IndexMap::new();
-    let mut updated_fields = IndexMap::new();
-    for (field_key, field_value) in new.root.iter() {
-      match self.root.get(field_key) {
-        Some(old_value) => {
-          if old_value != field_value {
-            updated_fields.insert(field_key.clone(), ValueDiff { old_value: old_value.clone(), new_value: field_value.clone() });
-          }
-        },
-        None => {
-          added_fields.insert(field_key.clone(), field_value.clone());
-        },
-      }
-    }
-    for (field_key, field_value) in self.root.iter() {
-      match new.root.get(field_key) {
-        Some(_) => {},
-        None => {
-          removed_fields.insert(field_key.clone(), field_value.clone());
-        },
-      }
-    }
-    ModelDiff { added_blocks, removed_blocks, updated_blocks, root: BlockDiff { added_fields, removed_fields, updated_fields } }
+    let root = BlockDiff::with_options(&self.root, &new.root, None, options);
+    ModelDiff { added_blocks, removed_blocks, updated_blocks, root }
   }
 }
 
@@ -252,6 +494,83 @@ impl PartialEq for BlockDiff {
 }
 impl Eq for BlockDiff {}
 
+impl BlockDiff {
+  pub fn new(old: &Block, new: &Block) -> BlockDiff {
+    BlockDiff::with_options(old, new, None, &DiffOptions::default())
+  }
+
+  /// Like `new`, applying the ignore rules for blocks of `block_type` (`None` for the root)
+  pub fn with_options(old: &Block, new: &Block, block_type: Option<&str>, options: &DiffOptions) -> BlockDiff {
+    let mut added_fields = IndexMap::new();
+    let mut removed_fields = IndexMap::new();
+    let mut updated_fields = IndexMap::new();
+    for (field_key, field_value) in new.iter().filter(|(k, _)| !options.ignores(block_type, k)) {
+      match old.get(field_key) {
+        Some(old_value) => {
+          if !options.values_equal(old_value, field_value) {
+            updated_fields.insert(field_key.clone(), ValueDiff { old_value: old_value.clone(), new_value: field_value.clone() });
+          }
+        },
+        None => {
+          added_fields.insert(field_key.clone(), field_value.clone());
+        },
+      }
+    }
+    for (field_key, field_value) in old.iter().filter(|(k, _)| !options.ignores(block_type, k)) {
+      if !new.contains_key(field_key) {
+        removed_fields.insert(field_key.clone(), field_value.clone());
+      }
+    }
+    BlockDiff { added_fields, removed_fields, updated_fields }
+  }
+  pub fn is_empty(&self) -> bool {
+    self.added_fields.is_empty() && self.removed_fields.is_empty() && self.updated_fields.is_empty()
+  }
+}
+
+/// Rules for which differences between two models are significant.  The default treats every difference as
+/// significant, including `1` vs `1.0`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffOptions {
+  /// Numbers closer than this are equal
+  pub abs_tolerance: f64,
+  /// Numbers closer than this fraction of the larger magnitude are equal
+  pub rel_tolerance: f64,
+  /// Whether integers and floats are compared by value (e.g. `1` equals `1.0`).  Implied by a non-zero tolerance.
+  pub numeric_equality: bool,
+  /// Fields ignored on every block and the root (e.g. `dateModified`)
+  pub ignored_fields: Vec<String>,
+  /// Fields ignored on blocks of each type
+  pub ignored_type_fields: HashMap<String, Vec<String>>,
+}
+
+impl DiffOptions {
+  pub fn ignores(&self, block_type: Option<&str>, field: &str) -> bool {
+    self.ignored_fields.iter().any(|f| f == field) || block_type
+      .and_then(|t| self.ignored_type_fields.get(t))
+      .is_some_and(|fields| fields.iter().any(|f| f == field))
+  }
+
+  /// Whether two values are equal, comparing the numbers within them according to the options
+  pub fn values_equal(&self, a: &Value, b: &Value) -> bool {
+    match (a, b) {
+      (Value::Number(a), Value::Number(b)) if a != b => {
+        if !self.numeric_equality && self.abs_tolerance == 0.0 && self.rel_tolerance == 0.0 {
+          return false;
+        }
+        let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) else { return false };
+        let difference = (a - b).abs();
+        difference == 0.0 || difference <= self.abs_tolerance || difference <= self.rel_tolerance * a.abs().max(b.abs())
+      },
+      (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.values_equal(a, b)),
+      (Value::Object(a), Value::Object(b)) => {
+        a.len() == b.len() && a.iter().all(|(k, a)| b.get(k).is_some_and(|b| self.values_equal(a, b)))
+      },
+      _ => a == b,
+    }
+  }
+}
+
 /// A concise representation of the differences between two models.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ModelDiff {
@@ -275,9 +594,7 @@ impl ModelDiff {
     self.added_blocks.is_empty() && 
     self.removed_blocks.is_empty() && 
     self.updated_blocks.is_empty() && 
-    self.root.added_fields.is_empty() && 
-    self.root.removed_fields.is_empty() && 
-    self.root.updated_fields.is_empty()
+    self.root.is_empty()
   }
 }
 
@@ -323,6 +640,7 @@ mod tests {
     assert_eq!(old.diff(&new), empty_diff);
     assert_eq!(new.diff(&old), empty_diff);
     assert!(empty_diff.is_empty());
+    assert_eq!(empty_diff.to_json_patch(&old), json!([]));
 
     old.root.insert("name".to_string(), json!("root"));
     old.blocks.insert("1".to_string(), {
@@ -355,6 +673,7 @@ mod tests {
     };
     assert!(!diff.is_empty());
     assert_eq!(old.diff(&new), diff);
+    assert_eq!(ModelDiff::from_json_patch(&diff.to_json_patch(&old)).unwrap(), diff);
 
     let diff = ModelDiff {
       added_blocks: {
@@ -380,6 +699,7 @@ mod tests {
     };
     assert!(!diff.is_empty());
     assert_eq!(new.diff(&old), diff);
+    assert_eq!(ModelDiff::from_json_patch(&diff.to_json_patch(&new)).unwrap(), diff);
 
     let mut new = old.clone();
     new.root.insert("name".to_string(), json!("root2"));
@@ -449,6 +769,179 @@ mod tests {
         },
       },
     });
+    for (from, to) in [(&old, &new), (&new, &old)] {
+      let diff = from.diff(to);
+      assert_eq!(ModelDiff::from_json_patch(&diff.to_json_patch(from)).unwrap(), diff);
+    }
   }
-}
 
+  #[test]
+  fn test_quantities() {
+    let mut model: Model = serde_json::from_value(json!({
+      "blocks": {"sc": {"type": "Spacecraft", "dryMass": 100000, "name": "sc"}},
+      "index": {"Spacecraft": ["sc"]},
+      "_quantityKinds": {"Spacecraft": {"dryMass": "Mass.g"}},
+    })).unwrap();
+    assert_eq!(model.metadata.quantity_kinds["Spacecraft"]["dryMass"], "Mass.g");
+    assert!(model.root.is_empty());
+
+    let mass = model.get_quantity("sc", "dryMass").unwrap();
+    assert_eq!((mass.value, mass.unit.symbol), (100000.0, "g"));
+    model.set_quantity("sc", "dryMass", Quantity::new(2.5, Unit::parse("kg").unwrap())).unwrap();
+    assert_eq!(model.blocks["sc"]["dryMass"], json!(2500.0));
+    assert!(matches!(
+      model.set_quantity("sc", "dryMass", Quantity::new(1.0, Unit::parse("m").unwrap())),
+      Err(ModelError::UnitError(UnitError::Incompatible { .. })),
+    ));
+    assert!(matches!(model.get_quantity("sc", "name"), Err(ModelError::UnitError(UnitError::NoQuantityKind { .. }))));
+
+    let round_tripped: Model = serde_json::from_str(&model.to_pretty_string()).unwrap();
+    assert_eq!(round_tripped.metadata, model.metadata);
+  }
+
+  #[test]
+  fn test_metadata_round_trip() {
+    let document = json!({
+      "blocks": {
+        "sc": {"type": "Spacecraft", "batteryPacks": ["bp"]},
+        "bp": {"type": "BatteryPack", "spacecraft": "sc"},
+      },
+      "index": {"Spacecraft": ["sc"], "BatteryPack": ["bp"], "Component": ["BatteryPack"]},
+      "name": "B",
+      "_blockNames": ["Spacecraft", "BatteryPack", "Component"],
+      "_quantityKinds": {"BatteryPack": {"esr": "Resistance"}},
+      "_relationships": {
+        "Spacecraft": {"batteryPacks": {"type": "ManySide", "types": ["BatteryPack"], "inverse": "spacecraft", "optional": true}},
+      },
+      "_supers": {"BatteryPack": ["Component"]},
+      "_abstractBlockTypes": ["Component"],
+      "migrated": "2024-01-01",
+      "issues": {},
+    });
+    let model: Model = serde_json::from_value(document.clone()).unwrap();
+    assert_eq!(model.root, Block::from_iter([("name".to_string(), json!("B"))]));
+    let relationship = model.metadata.relationship("Spacecraft", "batteryPacks").unwrap();
+    assert_eq!((relationship.kind.as_str(), relationship.inverse.as_deref()), ("ManySide", Some("spacecraft")));
+    assert_eq!(relationship.other["optional"], json!(true));
+    assert_eq!(model.metadata.supers("BatteryPack"), ["Component"]);
+    assert!(model.metadata.supers("Spacecraft").is_empty());
+    assert!(model.metadata.is_abstract("Component"));
+    assert_eq!(serde_json::to_value(&model).unwrap(), document);
+
+    let mut stripped = model.clone();
+    stripped.metadata = ModelMetadata::default();
+    assert!(model.diff(&stripped).is_empty());
+  }
+
+  #[test]
+  fn test_relationships() {
+    let mut model: Model = serde_json::from_value(json!({
+      "blocks": {
+        "sc": {"type": "Spacecraft", "name": "bp0", "batteryPacks": ["bp0", "bp1", "bp2"]},
+        "bp0": {"type": "BatteryPack", "spacecraft": "sc"},
+        "bp1": {"type": "BatteryPack", "spacecraft": "sc"},
+        "sheet": {"type": "Sheet", "name": "Sheet1"},
+        "cell": {"type": "Cell", "sheet": "sheet", "value": 1},
+      },
+      "index": {},
+      "_relationships": {
+        "Spacecraft": {"batteryPacks": {"type": "ManySide", "types": ["BatteryPack"], "inverse": "spacecraft"}},
+        "Component": {"spacecraft": {"type": "OneSide", "types": ["Spacecraft"], "inverse": "batteryPacks"}},
+      },
+      "_supers": {"BatteryPack": ["Component"]},
+    })).unwrap();
+
+    assert_eq!(model.related_ids("sc", "batteryPacks").unwrap(), ["bp0", "bp1", "bp2"]);
+    assert!(matches!(model.related("sc", "batteryPacks"), Err(ModelError::BlockNotFound(_))));
+    assert_eq!(model.related("bp0", "spacecraft").unwrap()[0].0, "sc");
+    assert!(matches!(model.related_ids("cell", "value"), Err(ModelError::NotARelationship { .. })));
+    // Undeclared fields of a type with relationships aren't followed, even if they hold a block ID
+    assert!(matches!(model.related_ids("sc", "name"), Err(ModelError::NotARelationship { .. })));
+    assert_eq!(model.related_ids("cell", "sheet").unwrap(), ["sheet"]);
+    assert_eq!(
+      model.dangling_references(),
+      vec![Reference { block: "sc".into(), field: "batteryPacks".into(), target: "bp2".into() }],
+    );
+    assert_eq!(model.referencing("sc").iter().map(|r| r.block.as_str()).collect::<Vec<_>>(), ["bp0", "bp1"]);
+
+    // Models without `_relationships` for a type rely on explicit declarations
+    assert!(model.referencing("sheet").is_empty());
+    model.declare_relationship("Cell", "sheet", Relationship::new("OneSide", &["Sheet"], None));
+    model.declare_relationship("Spacecraft", "batteryPacks", Relationship::new("OneSide", &[], None));
+    assert_eq!(model.metadata.relationship("Spacecraft", "batteryPacks").unwrap().kind, "ManySide");
+    assert_eq!(model.referencing("sheet"), vec![Reference { block: "cell".into(), field: "sheet".into(), target: "sheet".into() }]);
+    let written = serde_json::to_value(&model).unwrap();
+    assert!(written["_relationships"].get("Cell").is_none());
+  }
+
+  #[test]
+  fn test_type_hierarchy() {
+    let model: Model = serde_json::from_value(json!({
+      "blocks": {
+        "bp0": {"type": "BatteryPack", "name": "a"},
+        "bp1": {"type": "BatteryPack", "name": "b"},
+        "sp": {"type": "SolarPanel", "name": "a"},
+        "legacy": {"name": "a"},
+      },
+      "index": {
+        "Component": ["PowerComponent", "Thruster", "removed"],
+        "PowerComponent": ["BatteryPack"],
+        "BatteryPack": ["bp0", "bp1"],
+        "Thruster": ["legacy"],
+      },
+      "_supers": {"SolarPanel": ["PowerComponent"], "PowerComponent": ["Component"]},
+      "_abstractBlockTypes": ["Component", "PowerComponent"],
+      "_quantityKinds": {"Component": {"mass": "Mass"}},
+    })).unwrap();
+
+    assert_eq!(model.concrete_types_of("Component").collect::<Vec<_>>(), ["Thruster", "BatteryPack", "SolarPanel"]);
+    assert!(model.is_instance_of("sp", "Component"));
+    assert!(model.is_instance_of("legacy", "Component"));
+    assert!(!model.is_instance_of("legacy", "PowerComponent"));
+    assert!(!model.is_instance_of("missing", "Component"));
+    assert!(!model.is_type("removed"));
+
+    let filter = HashMap::from([("name".to_string(), json!("a"))]);
+    let ids = model.blocks_of_type_where("PowerComponent", &filter).map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, ["bp0", "sp"]);
+    assert_eq!(model.blocks_of_type_where("Component", &HashMap::new()).count(), 4);
+    assert_eq!(model.blocks_of_type_where("Unknown", &HashMap::new()).count(), 0);
+    assert_eq!(model.unit_of("sp", "mass").unwrap().symbol, "kg");
+  }
+
+  #[test]
+  fn test_diff_options() {
+    let old: Model = serde_json::from_value(json!({
+      "blocks": {
+        "a": {"type": "Battery", "mass": 1, "voltage": 3.3, "cells": [1.0, 2.0], "dateModified": "x", "note": "n"},
+        "b": {"type": "Panel", "note": "n"},
+      },
+      "index": {"Battery": ["a"], "Panel": ["b"]},
+      "dateModified": "x",
+    })).unwrap();
+    let mut new = old.clone();
+    let a = new.blocks.get_mut("a").unwrap();
+    a.insert("mass".into(), json!(1.0));
+    a.insert("voltage".into(), json!(3.3000001));
+    a.insert("cells".into(), json!([1, 2.0000001]));
+    a.insert("dateModified".into(), json!("y"));
+    new.root.insert("dateModified".into(), json!("y"));
+    assert_eq!(old.diff(&new).updated_blocks["a"].updated_fields.len(), 4);
+
+    let mut options = DiffOptions { numeric_equality: true, ..Default::default() };
+    assert_eq!(old.diff_with(&new, &options).updated_blocks["a"].updated_fields.keys().collect::<Vec<_>>(), ["voltage", "cells", "dateModified"]);
+    options.abs_tolerance = 1e-6;
+    options.ignored_fields.push("dateModified".into());
+    assert!(old.diff_with(&new, &options).is_empty());
+    options.abs_tolerance = 0.0;
+    options.rel_tolerance = 1e-6;
+    assert!(old.diff_with(&new, &options).is_empty());
+
+    new.blocks.get_mut("a").unwrap().insert("note".into(), json!("m"));
+    new.blocks.get_mut("b").unwrap().shift_remove("note");
+    options.ignored_type_fields.insert("Battery".into(), vec!["note".into()]);
+    let diff = old.diff_with(&new, &options);
+    assert!(!diff.updated_blocks.contains_key("a"));
+    assert!(diff.updated_blocks["b"].removed_fields.contains_key("note"));
+  }
+}
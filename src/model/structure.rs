@@ -0,0 +1,229 @@
+//! Structural changes to a Model that keep its `index` consistent with its blocks.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use serde_json::Value;
+use crate::model::sedaroml::{Block, Model, ModelError, Reference};
+
+/// A way in which a Model's `index` disagrees with its blocks, or a block references a block that doesn't exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+  /// A block isn't listed in the index under its type
+  UnindexedBlock { block: String, block_type: String },
+  /// A block is listed in the index under a type other than its own
+  MisindexedBlock { block: String, indexed_as: String, block_type: String },
+  /// An index entry is neither a block nor a block type
+  UnknownIndexEntry { block_type: String, entry: String },
+  DanglingReference(Reference),
+}
+
+impl fmt::Display for Inconsistency {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Inconsistency::UnindexedBlock { block, block_type } => write!(f, "Block `{}` is not indexed under its type `{}`", block, block_type),
+      Inconsistency::MisindexedBlock { block, indexed_as, block_type } => write!(f, "Block `{}` of type `{}` is indexed under `{}`", block, block_type, indexed_as),
+      Inconsistency::UnknownIndexEntry { block_type, entry } => write!(f, "Index entry `{}` under `{}` is neither a block nor a block type", entry, block_type),
+      Inconsistency::DanglingReference(r) => write!(f, "Field `{}` of block `{}` references missing block `{}`", r.field, r.block, r.target),
+    }
+  }
+}
+
+/// Characters of generated block IDs
+const ID_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
+impl Model {
+  /// A random 20 character block ID, like those assigned by Sedaro, that isn't used by the model
+  pub fn new_block_id(&self) -> String {
+    loop {
+      let state = RandomState::new();
+      let bits = ((state.hash_one(0u8) as u128) << 64) | state.hash_one(1u8) as u128;
+      let id = (0..20).map(|i| ID_ALPHABET[(bits >> (i * 6)) as usize & 63] as char).collect::<String>();
+      if !self.blocks.contains_key(&id) {
+        return id;
+      }
+    }
+  }
+
+  /// The first unused `temp-N` ID.  Sedaro replaces these with permanent IDs, and updates references to them, when
+  /// the model is saved.
+  pub fn new_temp_id(&self) -> String {
+    (0..).map(|n| format!("temp-{}", n)).find(|id| !self.blocks.contains_key(id)).unwrap()
+  }
+
+  fn block_type(block: &Block, id: &str) -> Result<String, ModelError> {
+    match block.get("type") {
+      Some(Value::String(block_type)) => Ok(block_type.clone()),
+      _ => Err(ModelError::FieldNotFound { block: id.to_string(), field: "type".to_string() }),
+    }
+  }
+
+  /// Lists a block type in the index under each of its supertypes, recursively
+  fn index_type(&mut self, block_type: &str) {
+    let supers = self.metadata.supers(block_type).to_vec();
+    for parent in supers {
+      let entries = self.index.entry(parent.clone()).or_default();
+      if !entries.iter().any(|entry| entry == block_type) {
+        entries.push(block_type.to_string());
+        self.index_type(&parent);
+      }
+    }
+  }
+
+  /// Adds a block, indexing it under its `type`.  If the block has an `id` field it must match `id`.
+  pub fn add_block(&mut self, id: &str, block: Block) -> Result<(), ModelError> {
+    if self.blocks.contains_key(id) {
+      return Err(ModelError::DuplicateBlock(id.to_string()));
+    }
+    if block.get("id").is_some_and(|own_id| own_id.as_str() != Some(id)) {
+      return Err(ModelError::InvalidBlock(format!("Block `{}` has a different `id` field", id)));
+    }
+    let block_type = Model::block_type(&block, id)?;
+    self.blocks.insert(id.to_string(), block);
+    self.index.entry(block_type.clone()).or_default().push(id.to_string());
+    self.index_type(&block_type);
+    Ok(())
+  }
+
+  /// Removes a block and its index entry.  With `cascade`, references to the block from the declared relationship
+  /// fields of other blocks are removed too; otherwise they are left dangling.
+  pub fn remove_block(&mut self, id: &str, cascade: bool) -> Result<Block, ModelError> {
+    let references = if cascade { self.referencing(id) } else { vec![] };
+    let block = self.blocks.shift_remove(id)
+      .ok_or_else(|| ModelError::BlockNotFound(format!("Block ID not found: {id}")))?;
+    for entries in self.index.values_mut() {
+      entries.retain(|entry| entry != id);
+    }
+    for reference in references {
+      let Some(value) = self.blocks.get_mut(&reference.block).and_then(|block| block.get_mut(&reference.field)) else { continue };
+      match value {
+        Value::Array(ids) => ids.retain(|target| target.as_str() != Some(id)),
+        Value::Object(ids) => { ids.shift_remove(id); },
+        _ => *value = Value::Null,
+      }
+    }
+    Ok(block)
+  }
+
+  /// Changes the type of a block, moving it within the index
+  pub fn retype_block(&mut self, id: &str, block_type: &str) -> Result<(), ModelError> {
+    let block = self.block_by_id_mut(id)?;
+    let old_type = Model::block_type(block, id)?;
+    block.insert("type".to_string(), Value::String(block_type.to_string()));
+    if let Some(entries) = self.index.get_mut(&old_type) {
+      entries.retain(|entry| entry != id);
+    }
+    self.index.entry(block_type.to_string()).or_default().push(id.to_string());
+    self.index_type(block_type);
+    Ok(())
+  }
+
+  /// Checks that every typed block is indexed under its type, that every index entry is a block or a block type, and
+  /// that no declared relationship references a missing block
+  pub fn validate(&self) -> Vec<Inconsistency> {
+    let mut inconsistencies = vec![];
+    for (id, block) in &self.blocks {
+      let Some(block_type) = block.get("type").and_then(|t| t.as_str()) else { continue };
+      if !self.index.get(block_type).is_some_and(|entries| entries.contains(id)) {
+        inconsistencies.push(Inconsistency::UnindexedBlock { block: id.clone(), block_type: block_type.to_string() });
+      }
+    }
+    for (indexed_as, entries) in &self.index {
+      for entry in entries {
+        match self.blocks.get(entry) {
+          Some(block) => match block.get("type").and_then(|t| t.as_str()) {
+            Some(block_type) if block_type != indexed_as => inconsistencies.push(Inconsistency::MisindexedBlock {
+              block: entry.clone(), indexed_as: indexed_as.clone(), block_type: block_type.to_string(),
+            }),
+            _ => {},
+          },
+          None => {
+            if !self.is_type(entry) {
+              inconsistencies.push(Inconsistency::UnknownIndexEntry { block_type: indexed_as.clone(), entry: entry.clone() });
+            }
+          },
+        }
+      }
+    }
+    inconsistencies.extend(self.dangling_references().into_iter().map(Inconsistency::DanglingReference));
+    inconsistencies
+  }
+
+  /// Replaces references to one block ID with another in the declared relationship fields of every block, e.g. once
+  /// Sedaro has assigned a permanent ID to a `temp-N` block
+  pub fn rename_references(&mut self, from: &str, to: &str) {
+    for reference in self.referencing(from) {
+      let Some(value) = self.blocks.get_mut(&reference.block).and_then(|block| block.get_mut(&reference.field)) else { continue };
+      match value {
+        Value::String(target) => *target = to.to_string(),
+        Value::Array(ids) => ids.iter_mut().filter(|target| target.as_str() == Some(from)).for_each(|target| *target = Value::from(to)),
+        Value::Object(ids) => {
+          if let Some(data) = ids.shift_remove(from) {
+            ids.insert(to.to_string(), data);
+          }
+        },
+        _ => {},
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use crate::model::sedaroml::{Block, Model, ModelError, Reference};
+  use super::Inconsistency;
+
+  fn block(value: serde_json::Value) -> Block {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn test_structural_changes() {
+    let mut model: Model = serde_json::from_value(json!({
+      "blocks": {"sc": {"type": "Spacecraft", "batteryPacks": []}},
+      "index": {"Spacecraft": ["sc"]},
+      "_relationships": {"Spacecraft": {"batteryPacks": {"type": "ManySide", "types": ["BatteryPack"]}}},
+      "_supers": {"BatteryPack": ["PowerComponent"], "PowerComponent": ["Component"]},
+    })).unwrap();
+
+    let id = model.new_temp_id();
+    assert_eq!(id, "temp-0");
+    model.add_block(&id, block(json!({"type": "BatteryPack"}))).unwrap();
+    assert_eq!(model.new_temp_id(), "temp-1");
+    assert_eq!(model.index["BatteryPack"], ["temp-0"]);
+    assert_eq!(model.index["PowerComponent"], ["BatteryPack"]);
+    assert_eq!(model.index["Component"], ["PowerComponent"]);
+    assert!(matches!(model.add_block(&id, block(json!({"type": "BatteryPack"}))), Err(ModelError::DuplicateBlock(_))));
+    assert!(matches!(model.add_block("x", block(json!({"name": "x"}))), Err(ModelError::FieldNotFound { .. })));
+
+    let generated = model.new_block_id();
+    assert_eq!(generated.len(), 20);
+    model.add_block(&generated, block(json!({"type": "BatteryPack", "id": generated}))).unwrap();
+    model.blocks.get_mut("sc").unwrap().insert("batteryPacks".into(), json!(["temp-0", generated]));
+    assert!(model.validate().is_empty());
+
+    model.retype_block("temp-0", "SolarPanel").unwrap();
+    assert_eq!(model.index["BatteryPack"], [generated.clone()]);
+    assert_eq!(model.index["SolarPanel"], ["temp-0"]);
+
+    model.remove_block("temp-0", false).unwrap();
+    assert_eq!(
+      model.validate(),
+      vec![Inconsistency::DanglingReference(Reference { block: "sc".into(), field: "batteryPacks".into(), target: "temp-0".into() })],
+    );
+    model.rename_references("temp-0", "bp");
+    model.add_block("bp", block(json!({"type": "BatteryPack"}))).unwrap();
+    model.remove_block("bp", true).unwrap();
+    assert_eq!(model.blocks["sc"]["batteryPacks"], json!([generated]));
+    assert!(model.validate().is_empty());
+
+    model.index.get_mut("Spacecraft").unwrap().push("gone".into());
+    model.blocks.get_mut("sc").unwrap().insert("type".into(), json!("Satellite"));
+    assert_eq!(model.validate(), vec![
+      Inconsistency::UnindexedBlock { block: "sc".into(), block_type: "Satellite".into() },
+      Inconsistency::MisindexedBlock { block: "sc".into(), indexed_as: "Spacecraft".into(), block_type: "Satellite".into() },
+      Inconsistency::UnknownIndexEntry { block_type: "Spacecraft".into(), entry: "gone".into() },
+    ]);
+  }
+}
@@ -1,11 +1,14 @@
 use indexmap::IndexMap;
 use serde::{Serialize, Deserialize};
-use crate::model::sedaroml::{Model, Block};
+use crate::model::sedaroml::{Model, ModelMetadata, Block};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct TempModel {
   blocks: IndexMap<String, Block>,
   index: IndexMap<String, Vec<String>>,
+  // Must precede `root` so that the metadata fields are taken out before the rest of the root is collected
+  #[serde(flatten)]
+  metadata: ModelMetadata,
   #[serde(flatten)]
   root: Block,
 }
@@ -15,17 +18,11 @@ impl From<TempModel> for Model {
     let mut instance = Self {
       blocks: temp.blocks,
       index: temp.index,
+      metadata: temp.metadata,
       root: temp.root,
     };
-    instance.root.swap_remove("_blockNames");
-    instance.root.swap_remove("_quantityKinds");
-    instance.root.swap_remove("_relationships");
-    instance.root.swap_remove("_supers");
-    instance.root.swap_remove("_abstractBlockTypes");
     instance.root.swap_remove("blocks");
     instance.root.swap_remove("index");
-    instance.root.swap_remove("migrated");
-    instance.root.swap_remove("issues");
     instance
   }
 }
\ No newline at end of file
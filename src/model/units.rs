@@ -0,0 +1,242 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnitError {
+  UnknownUnit(String),
+  UnknownQuantityKind(String),
+  /// A unit was given for a quantity kind it doesn't measure (e.g. `Mass.m`)
+  WrongQuantityKind { unit: String, kind: String },
+  /// The units measure different quantity kinds and can't be converted between
+  Incompatible { from: String, to: String },
+  /// The model doesn't declare a quantity kind for the field
+  NoQuantityKind { block: String, field: String },
+}
+
+impl fmt::Display for UnitError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      UnitError::UnknownUnit(unit) => write!(f, "Unknown unit `{}`", unit),
+      UnitError::UnknownQuantityKind(kind) => write!(f, "Unknown quantity kind `{}`", kind),
+      UnitError::WrongQuantityKind { unit, kind } => write!(f, "`{}` is not a unit of {}", unit, kind),
+      UnitError::Incompatible { from, to } => write!(f, "Cannot convert from {} to {}", from, to),
+      UnitError::NoQuantityKind { block, field } => write!(f, "No quantity kind is declared for field `{}` of block `{}`", field, block),
+    }
+  }
+}
+
+impl std::error::Error for UnitError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuantityKind {
+  Dimensionless,
+  Mass,
+  Length,
+  Time,
+  Angle,
+  AngularRate,
+  Velocity,
+  Temperature,
+  Current,
+  Voltage,
+  Resistance,
+  Charge,
+  Power,
+  Energy,
+  Frequency,
+}
+
+impl QuantityKind {
+  const ALL: [QuantityKind; 15] = [
+    QuantityKind::Dimensionless, QuantityKind::Mass, QuantityKind::Length, QuantityKind::Time, QuantityKind::Angle,
+    QuantityKind::AngularRate, QuantityKind::Velocity, QuantityKind::Temperature, QuantityKind::Current,
+    QuantityKind::Voltage, QuantityKind::Resistance, QuantityKind::Charge, QuantityKind::Power, QuantityKind::Energy,
+    QuantityKind::Frequency,
+  ];
+
+  /// The SI unit every other unit of the kind is converted through
+  pub fn base_unit(&self) -> Unit {
+    *UNITS.iter().find(|u| u.kind == *self).unwrap()
+  }
+}
+
+impl fmt::Display for QuantityKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(self, f)
+  }
+}
+
+impl FromStr for QuantityKind {
+  type Err = UnitError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    QuantityKind::ALL.into_iter().find(|kind| kind.to_string().eq_ignore_ascii_case(s))
+      .ok_or_else(|| UnitError::UnknownQuantityKind(s.to_string()))
+  }
+}
+
+/// A unit of measure.  A value `v` in the unit is `v * factor + offset` in the base unit of its kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+  pub symbol: &'static str,
+  pub kind: QuantityKind,
+  factor: f64,
+  offset: f64,
+}
+
+const fn unit(symbol: &'static str, kind: QuantityKind, factor: f64) -> Unit {
+  Unit { symbol, kind, factor, offset: 0.0 }
+}
+
+/// Known units, with the base unit of each kind listed first
+const UNITS: &[Unit] = &[
+  unit("", QuantityKind::Dimensionless, 1.0),
+  unit("%", QuantityKind::Dimensionless, 0.01),
+  unit("kg", QuantityKind::Mass, 1.0),
+  unit("g", QuantityKind::Mass, 1e-3),
+  unit("mg", QuantityKind::Mass, 1e-6),
+  unit("lb", QuantityKind::Mass, 0.45359237),
+  unit("m", QuantityKind::Length, 1.0),
+  unit("km", QuantityKind::Length, 1e3),
+  unit("cm", QuantityKind::Length, 1e-2),
+  unit("mm", QuantityKind::Length, 1e-3),
+  unit("ft", QuantityKind::Length, 0.3048),
+  unit("in", QuantityKind::Length, 0.0254),
+  unit("s", QuantityKind::Time, 1.0),
+  unit("ms", QuantityKind::Time, 1e-3),
+  unit("min", QuantityKind::Time, 60.0),
+  unit("hr", QuantityKind::Time, 3600.0),
+  unit("day", QuantityKind::Time, 86400.0),
+  unit("rad", QuantityKind::Angle, 1.0),
+  unit("deg", QuantityKind::Angle, std::f64::consts::PI / 180.0),
+  unit("arcmin", QuantityKind::Angle, std::f64::consts::PI / 10800.0),
+  unit("arcsec", QuantityKind::Angle, std::f64::consts::PI / 648000.0),
+  unit("rad/s", QuantityKind::AngularRate, 1.0),
+  unit("deg/s", QuantityKind::AngularRate, std::f64::consts::PI / 180.0),
+  unit("rpm", QuantityKind::AngularRate, std::f64::consts::PI / 30.0),
+  unit("m/s", QuantityKind::Velocity, 1.0),
+  unit("km/s", QuantityKind::Velocity, 1e3),
+  unit("km/hr", QuantityKind::Velocity, 1.0 / 3.6),
+  unit("K", QuantityKind::Temperature, 1.0),
+  Unit { symbol: "degC", kind: QuantityKind::Temperature, factor: 1.0, offset: 273.15 },
+  Unit { symbol: "degF", kind: QuantityKind::Temperature, factor: 5.0 / 9.0, offset: 273.15 - 32.0 * 5.0 / 9.0 },
+  unit("A", QuantityKind::Current, 1.0),
+  unit("mA", QuantityKind::Current, 1e-3),
+  unit("V", QuantityKind::Voltage, 1.0),
+  unit("mV", QuantityKind::Voltage, 1e-3),
+  unit("kV", QuantityKind::Voltage, 1e3),
+  unit("ohm", QuantityKind::Resistance, 1.0),
+  unit("mohm", QuantityKind::Resistance, 1e-3),
+  unit("kohm", QuantityKind::Resistance, 1e3),
+  unit("C", QuantityKind::Charge, 1.0),
+  unit("Ah", QuantityKind::Charge, 3600.0),
+  unit("mAh", QuantityKind::Charge, 3.6),
+  unit("W", QuantityKind::Power, 1.0),
+  unit("mW", QuantityKind::Power, 1e-3),
+  unit("kW", QuantityKind::Power, 1e3),
+  unit("J", QuantityKind::Energy, 1.0),
+  unit("kJ", QuantityKind::Energy, 1e3),
+  unit("Wh", QuantityKind::Energy, 3600.0),
+  unit("kWh", QuantityKind::Energy, 3.6e6),
+  unit("Hz", QuantityKind::Frequency, 1.0),
+  unit("kHz", QuantityKind::Frequency, 1e3),
+  unit("MHz", QuantityKind::Frequency, 1e6),
+  unit("GHz", QuantityKind::Frequency, 1e9),
+];
+
+impl Unit {
+  pub fn parse(symbol: &str) -> Result<Unit, UnitError> {
+    UNITS.iter().find(|u| u.symbol == symbol).copied().ok_or_else(|| UnitError::UnknownUnit(symbol.to_string()))
+  }
+
+  /// Parses a quantity kind optionally qualified with a unit, as declared in `_quantityKinds` (e.g. `Mass` or
+  /// `Mass.g`).  A bare quantity kind means its base unit.
+  pub fn from_spec(spec: &str) -> Result<Unit, UnitError> {
+    let (kind, symbol) = match spec.split_once('.') {
+      Some((kind, symbol)) => (kind.parse::<QuantityKind>()?, Some(symbol)),
+      None => (spec.parse::<QuantityKind>()?, None),
+    };
+    let Some(symbol) = symbol else {
+      return Ok(kind.base_unit());
+    };
+    let unit = Unit::parse(symbol)?;
+    if unit.kind != kind {
+      return Err(UnitError::WrongQuantityKind { unit: unit.symbol.to_string(), kind: kind.to_string() });
+    }
+    Ok(unit)
+  }
+
+  /// The `Kind.unit` form of the unit, as accepted by `from_spec`
+  pub fn spec(&self) -> String {
+    match self.symbol {
+      "" => self.kind.to_string(),
+      symbol => format!("{}.{}", self.kind, symbol),
+    }
+  }
+
+  pub fn convert(&self, value: f64, to: &Unit) -> Result<f64, UnitError> {
+    if self.kind != to.kind {
+      return Err(UnitError::Incompatible { from: self.to_string(), to: to.to_string() });
+    }
+    if self.symbol == to.symbol {
+      return Ok(value);
+    }
+    Ok((value * self.factor + self.offset - to.offset) / to.factor)
+  }
+}
+
+impl fmt::Display for Unit {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} [{}]", self.kind, self.symbol)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+  pub value: f64,
+  pub unit: Unit,
+}
+
+impl Quantity {
+  pub fn new(value: f64, unit: Unit) -> Quantity {
+    Quantity { value, unit }
+  }
+  pub fn to(&self, unit: &Unit) -> Result<Quantity, UnitError> {
+    Ok(Quantity { value: self.unit.convert(self.value, unit)?, unit: *unit })
+  }
+}
+
+impl fmt::Display for Quantity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} {}", self.value, self.unit.symbol)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9 * b.abs().max(1.0)
+  }
+
+  #[test]
+  fn test_conversions() {
+    let g = Unit::parse("g").unwrap();
+    let kg = Unit::from_spec("Mass").unwrap();
+    assert_eq!(kg.symbol, "kg");
+    assert!(close(g.convert(100000.0, &kg).unwrap(), 100.0));
+    assert!(close(Quantity::new(180.0, Unit::parse("deg").unwrap()).to(&Unit::parse("rad").unwrap()).unwrap().value, std::f64::consts::PI));
+    assert!(close(Unit::parse("degC").unwrap().convert(100.0, &Unit::parse("degF").unwrap()).unwrap(), 212.0));
+    assert!(close(Unit::parse("degF").unwrap().convert(32.0, &Unit::parse("K").unwrap()).unwrap(), 273.15));
+
+    assert_eq!(
+      g.convert(1.0, &Unit::parse("m").unwrap()),
+      Err(UnitError::Incompatible { from: "Mass [g]".into(), to: "Length [m]".into() }),
+    );
+    assert_eq!(Unit::from_spec("mass.g").unwrap().spec(), "Mass.g");
+    assert_eq!(Unit::from_spec("Mass.m"), Err(UnitError::WrongQuantityKind { unit: "m".into(), kind: "Mass".into() }));
+    assert_eq!(Unit::from_spec("Heft"), Err(UnitError::UnknownQuantityKind("Heft".into())));
+    assert_eq!(Unit::parse("furlong"), Err(UnitError::UnknownUnit("furlong".into())));
+  }
+}
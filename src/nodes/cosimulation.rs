@@ -6,7 +6,7 @@ use log::{debug, info, warn};
 use std::time::{Duration, Instant};
 use ureq;
 use std::borrow::{Borrow, BorrowMut};
-use std::sync::mpsc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread;
 use crate::commands::{NodeCommands, NodeResponses};
 use crate::nodes::sedaro::SedaroCredentials;
@@ -18,6 +18,7 @@ pub struct Cosimulation {
   rep: Option<Model>,
   tx: mpsc::Sender<NodeCommands>,
   rx: Arc<Mutex<mpsc::Receiver<NodeResponses>>>,
+  thread: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,7 +40,7 @@ impl Cosimulation {
 
     let (tx_to_node, rx_in_node) = mpsc::channel::<NodeCommands>();
     let (tx_to_exchange, rx_in_exchange) = mpsc::channel::<NodeResponses>();
-    thread::spawn(move || {
+    let handle = thread::spawn(move || {
       // Setup
       let url = |job_id: String| -> String { format!("{host_url}/simulations/jobs/{job_id}/externals/{agent_id}/{external_state_id}") };
       let auth_header = match credentials {
@@ -89,8 +90,8 @@ impl Cosimulation {
               },
               NodeCommands::ResolveConflict(_) => {},
               NodeCommands::Stop => {
-                running_job_id = None;
                 tx_to_exchange.send(NodeResponses::Stopped).unwrap();
+                break;
               },
               NodeCommands::Changed(diff) => {
                 let t = Instant::now();
@@ -105,7 +106,8 @@ impl Cosimulation {
               NodeCommands::Done => {},
             }
           },
-          Err(_) => {},
+          Err(RecvTimeoutError::Disconnected) => break,
+          Err(RecvTimeoutError::Timeout) => {},
         }
         if running_job_id.is_some() {
           let job_id = running_job_id.clone().unwrap();
@@ -131,6 +133,7 @@ impl Cosimulation {
       rep: None,
       tx: tx_to_node,
       rx: Arc::new(Mutex::new(rx_in_exchange)),
+      thread: Arc::new(Mutex::new(Some(handle))),
     };
     Arc::new(Mutex::new(exchangeable))
   }
@@ -154,6 +157,7 @@ impl Exchangeable for Cosimulation {
   }
   fn tx(&self) -> &mpsc::Sender<NodeCommands> { &self.tx }
   fn rx(&self) -> &Arc<Mutex<mpsc::Receiver<NodeResponses>>> { &self.rx }
+  fn take_thread(&mut self) -> Option<thread::JoinHandle<()>> { self.thread.lock().unwrap().take() }
   fn refresh_rep(&mut self) {
     self.rep = Some(read_model(&self.sedaroml_filename()).unwrap_or_else(
       |e| panic!("{}: Failed to read SedaroML: {:?}", self.identifier(), e)
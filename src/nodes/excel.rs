@@ -1,13 +1,13 @@
 use crate::commands::{ConflictResolutions, NodeCommands, NodeResponses};
-use crate::model::sedaroml::{read_model, Model, ModelDiff};
+use crate::conflicts::{merge_with_base, save_base};
+use crate::model::sedaroml::{read_model, write_model, Model, ModelDiff};
 use crate::nodes::traits::Exchangeable;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use pyo3::prelude::*;
 use std::borrow::{Borrow, BorrowMut};
-use crate::utils::python_signal_handler;
-use std::sync::mpsc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread::{self};
 use log::{debug, error};
 use notify_debouncer_mini::{
@@ -25,6 +25,7 @@ pub struct Excel {
   rep: Option<Model>,
   tx: mpsc::Sender<NodeCommands>,
   rx: Arc<Mutex<mpsc::Receiver<NodeResponses>>>,
+  thread: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
 }
 
 impl Excel {
@@ -38,7 +39,7 @@ impl Excel {
 
     let (tx_to_node, rx_in_node) = mpsc::channel::<NodeCommands>();
     let (tx_to_exchange, rx_in_exchange) = mpsc::channel::<NodeResponses>();
-    thread::spawn(move || {
+    let handle = thread::spawn(move || {
       // Setup
       let _excel_filename = excel_filename.clone();
       let _sedaroml_filename = sedaroml_filename_clone.clone();
@@ -84,6 +85,9 @@ impl Excel {
                     continue;
                   }
                 }
+                save_base(&sedaroml_filename_clone).unwrap_or_else(
+                  |e| panic!("{}: Failed to save base SedaroML: {:?}", identifier_clone, e)
+                );
                 watcher.watch(&Path::new(&excel_filename), RecursiveMode::Recursive).unwrap_or_else(|e| panic!("Failed to watch path: {}: {}", excel_filename, e));
                 tx_to_exchange.send(NodeResponses::Started).unwrap();
               },
@@ -100,12 +104,39 @@ impl Excel {
                       |e| panic!("{}: Failed to convert Excel to SedaroML: {}", identifier_clone, e)
                     );
                   },
+                  ConflictResolutions::Merge => {
+                    let temp = NamedTempFile::new().unwrap();
+                    excel_to_sedaroml(&excel_filename, temp.path().to_str().unwrap()).unwrap_or_else(
+                      |e| panic!("{}: Failed to convert Excel to SedaroML: {}", identifier_clone, e)
+                    );
+                    let incoming = read_model(temp.path().to_str().unwrap()).unwrap_or_else(
+                      |e| panic!("{}: Failed to read SedaroML: {:?}", identifier_clone, e)
+                    );
+                    let merged = merge_with_base(&identifier_clone, &sedaroml_filename_clone, &incoming).unwrap_or_else(
+                      |e| panic!("{}: Failed to merge SedaroML: {:?}", identifier_clone, e)
+                    );
+                    write_model(&sedaroml_filename_clone, &merged).unwrap_or_else(
+                      |e| panic!("{}: Failed to write SedaroML to file: {:?}", identifier_clone, e)
+                    );
+                    sedaroml_to_excel(&sedaroml_filename_clone, &excel_filename).unwrap_or_else(
+                      |e| panic!("{}: Failed to convert SedaroML to Excel: {}", identifier_clone, e)
+                    );
+                  },
                 }
+                save_base(&sedaroml_filename_clone).unwrap_or_else(
+                  |e| panic!("{}: Failed to save base SedaroML: {:?}", identifier_clone, e)
+                );
                 tx_to_exchange.send(NodeResponses::ConflictResolved(t.elapsed())).unwrap();
                 watcher.watch(&Path::new(&excel_filename), RecursiveMode::Recursive).unwrap_or_else(|e| panic!("Failed to watch path: {}: {}", excel_filename, e));
                 tx_to_exchange.send(NodeResponses::Started).unwrap();
               },
-              NodeCommands::Stop => { tx_to_exchange.send(NodeResponses::Stopped).unwrap() },
+              NodeCommands::Stop => {
+                if let Err(e) = save_base(&sedaroml_filename_clone) {
+                  debug!("{}: Base SedaroML not saved: {:?}", identifier_clone, e);
+                }
+                tx_to_exchange.send(NodeResponses::Stopped).unwrap();
+                break;
+              },
               NodeCommands::Changed(diff) => {
                 let t = Instant::now();
                 reconcile_diff_to_excel(&sedaroml_filename_clone, &diff, &excel_filename).unwrap_or_else(
@@ -116,9 +147,9 @@ impl Excel {
               NodeCommands::Done => {},
             }
           },
-          Err(_) => {},
+          Err(RecvTimeoutError::Disconnected) => break,
+          Err(RecvTimeoutError::Timeout) => {},
         };
-        python_signal_handler().unwrap();
       }
     });
 
@@ -129,6 +160,7 @@ impl Excel {
       rep: None,
       tx: tx_to_node,
       rx: Arc::new(Mutex::new(rx_in_exchange)),
+      thread: Arc::new(Mutex::new(Some(handle))),
     };
     Arc::new(Mutex::new(exchangeable))
   }
@@ -152,6 +184,7 @@ impl Exchangeable for Excel {
   }
   fn tx(&self) -> &mpsc::Sender<NodeCommands> { &self.tx }
   fn rx(&self) -> &Arc<Mutex<mpsc::Receiver<NodeResponses>>> { &self.rx }
+  fn take_thread(&mut self) -> Option<thread::JoinHandle<()>> { self.thread.lock().unwrap().take() }
   fn refresh_rep(&mut self) {
     self.rep = Some(read_model(&self.sedaroml_filename()).unwrap_or_else(
       |e| panic!("{}: Failed to read SedaroML: {:?}", self.identifier(), e)
@@ -8,9 +8,10 @@ use std::time::{Duration, Instant};
 use ureq;
 use crate::metadata::{read_metadata, write_metadata};
 use std::borrow::{Borrow, BorrowMut};
-use std::sync::mpsc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread;
 use crate::commands::{ConflictResolutions, NodeCommands, NodeResponses};
+use crate::conflicts::{merge_with_base, save_base};
 
 #[derive(Clone)]
 pub enum SedaroCredentials {
@@ -25,6 +26,7 @@ pub struct Sedaro {
   rep: Option<Model>,
   tx: mpsc::Sender<NodeCommands>,
   rx: Arc<Mutex<mpsc::Receiver<NodeResponses>>>,
+  thread: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
 }
 
 impl Sedaro {
@@ -36,7 +38,7 @@ impl Sedaro {
 
     let (tx_to_node, rx_in_node) = mpsc::channel::<NodeCommands>();
     let (tx_to_exchange, rx_in_exchange) = mpsc::channel::<NodeResponses>();
-    thread::spawn(move || {
+    let handle = thread::spawn(move || {
       // Setup
       let url = format!("{}/models/branches/{}", host_url, branch_id);
       let auth_header = match credentials {
@@ -73,6 +75,9 @@ impl Sedaro {
                     continue;
                   }
                 }
+                save_base(&sedaroml_filename_clone).unwrap_or_else(
+                  |e| panic!("{}: Failed to save base SedaroML: {:?}", identifier_clone, e)
+                );
                 running = true;
                 tx_to_exchange.send(NodeResponses::Started).unwrap() 
               },
@@ -97,14 +102,37 @@ impl Sedaro {
                       |e| panic!("{}: Failed to write metadata to file: {:?}", identifier_clone, e)
                     );
                   }
+                  ConflictResolutions::Merge => {
+                    let (incoming, _) = get_sedaro_model(&url, &auth_header);
+                    let merged = merge_with_base(&identifier_clone, &sedaroml_filename_clone, &incoming).unwrap_or_else(
+                      |e| panic!("{}: Failed to merge SedaroML: {:?}", identifier_clone, e)
+                    );
+                    // Send the merge as a diff so that blocks it removed are deleted in Sedaro too
+                    let put_url = format!("{}/template", &url);
+                    put_sedaro_model_with_diff(&put_url, &auth_header, &merged, &incoming.diff(&merged));
+                    // Fetch the merged model again in order to get the IDs Sedaro assigned to any new blocks
+                    let (model, date_modified) = get_sedaro_model(&url, &auth_header);
+                    write_model(&sedaroml_filename_clone, &model).unwrap_or_else(
+                      |e| panic!("{}: Failed to write SedaroML to file: {:?}", identifier_clone, e)
+                    );
+                    write_metadata(&metadata_filename, &date_modified).unwrap_or_else(
+                      |e| panic!("{}: Failed to write metadata to file: {:?}", identifier_clone, e)
+                    );
+                  }
                 }
+                save_base(&sedaroml_filename_clone).unwrap_or_else(
+                  |e| panic!("{}: Failed to save base SedaroML: {:?}", identifier_clone, e)
+                );
                 tx_to_exchange.send(NodeResponses::ConflictResolved(i.elapsed())).unwrap();
                 running = true;
                 tx_to_exchange.send(NodeResponses::Started).unwrap()
               },
               NodeCommands::Stop => {
-                running = false;
+                if let Err(e) = save_base(&sedaroml_filename_clone) {
+                  debug!("{}: Base SedaroML not saved: {:?}", identifier_clone, e);
+                }
                 tx_to_exchange.send(NodeResponses::Stopped).unwrap();
+                break;
               },
               NodeCommands::Changed(diff) => {
                 let t = Instant::now();
@@ -129,7 +157,8 @@ impl Sedaro {
               NodeCommands::Done => {},
             }
           },
-          Err(_) => {},
+          Err(RecvTimeoutError::Disconnected) => break,
+          Err(RecvTimeoutError::Timeout) => {},
         }
         if running {
           debug!("{}: Checking for changes at: {}", identifier_clone, &url);
@@ -157,6 +186,7 @@ impl Sedaro {
       rep: None,
       tx: tx_to_node,
       rx: Arc::new(Mutex::new(rx_in_exchange)),
+      thread: Arc::new(Mutex::new(Some(handle))),
     };
     Arc::new(Mutex::new(exchangeable))
   }
@@ -180,6 +210,7 @@ impl Exchangeable for Sedaro {
   }
   fn tx(&self) -> &mpsc::Sender<NodeCommands> { &self.tx }
   fn rx(&self) -> &Arc<Mutex<mpsc::Receiver<NodeResponses>>> { &self.rx }
+  fn take_thread(&mut self) -> Option<thread::JoinHandle<()>> { self.thread.lock().unwrap().take() }
   fn refresh_rep(&mut self) {
     self.rep = Some(read_model(&self.sedaroml_filename()).unwrap_or_else(
       |e| panic!("{}: Failed to read SedaroML: {:?}", self.identifier(), e)
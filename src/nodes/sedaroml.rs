@@ -5,7 +5,7 @@ use crate::model::sedaroml::Model;
 use super::traits::Exchangeable;
 use crate::model::sedaroml::read_model;
 use std::borrow::{Borrow, BorrowMut};
-use std::sync::mpsc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use crate::commands::{NodeCommands, NodeResponses};
 use std::thread;
 use log::debug;
@@ -17,6 +17,7 @@ pub struct SedaroML {
   rep: Option<Model>,
   tx: mpsc::Sender<NodeCommands>,
   rx: Arc<Mutex<mpsc::Receiver<NodeResponses>>>,
+  thread: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
 }
 
 impl SedaroML {
@@ -26,7 +27,7 @@ impl SedaroML {
     let filename_clone = filename.clone();
     let (tx_to_node, rx_in_node) = mpsc::channel::<NodeCommands>();
     let (tx_to_exchange, rx_in_exchange) = mpsc::channel::<NodeResponses>();
-    thread::spawn(move || {
+    let handle = thread::spawn(move || {
       loop {
         match rx_in_node.recv_timeout(Duration::from_millis(100)) {
           Ok(command) => {
@@ -39,12 +40,16 @@ impl SedaroML {
                 }
                 tx_to_exchange.send(NodeResponses::Started).unwrap() 
               },
-              NodeCommands::Stop => { tx_to_exchange.send(NodeResponses::Stopped).unwrap() },
+              NodeCommands::Stop => {
+                tx_to_exchange.send(NodeResponses::Stopped).unwrap();
+                break;
+              },
               NodeCommands::Changed(_) => { tx_to_exchange.send(NodeResponses::Done(Duration::from_secs(0))).unwrap() },
               NodeCommands::Done => {},
             }
           },
-          Err(_) => {},
+          Err(RecvTimeoutError::Disconnected) => break,
+          Err(RecvTimeoutError::Timeout) => {},
         }
       }
     });
@@ -55,6 +60,7 @@ impl SedaroML {
       rep: None,
       tx: tx_to_node,
       rx: Arc::new(Mutex::new(rx_in_exchange)),
+      thread: Arc::new(Mutex::new(Some(handle))),
     };
     Arc::new(Mutex::new(exchangeable))
   }
@@ -78,6 +84,7 @@ impl Exchangeable for SedaroML {
   }
   fn tx(&self) -> &mpsc::Sender<NodeCommands> { &self.tx }
   fn rx(&self) -> &Arc<Mutex<mpsc::Receiver<NodeResponses>>> { &self.rx }
+  fn take_thread(&mut self) -> Option<thread::JoinHandle<()>> { self.thread.lock().unwrap().take() }
   fn refresh_rep(&mut self) {
     self.rep = Some(read_model(&self.sedaroml_filename()).unwrap_or_else(
       |e| panic!("{}: Failed to read SedaroML: {:?}", self.identifier(), e)
@@ -1,8 +1,9 @@
 use crate::model::sedaroml::Model;
 use crate::commands::NodeCommands;
 use crate::commands::NodeResponses;
-use std::sync::mpsc::{Receiver, Sender, RecvTimeoutError};
+use std::sync::mpsc::{Receiver, Sender, RecvError, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 pub trait Exchangeable {
@@ -22,6 +23,14 @@ pub trait Exchangeable {
     self.rx_from_node()
   }
   fn rx_from_node(&self) -> NodeResponses { self.rx().lock().unwrap().recv().unwrap() }
+  /// Like `tx_to_node_blocking` but returns an error instead of panicking if the Node has hung up
+  fn try_tx_to_node_blocking(&self, command: NodeCommands) -> Result<NodeResponses, RecvError> {
+    self.tx().send(command).map_err(|_| RecvError)?;
+    self.try_rx_from_node()
+  }
+  fn try_rx_from_node(&self) -> Result<NodeResponses, RecvError> { self.rx().lock().unwrap().recv() }
   fn rx_from_node_timeout(&self, timeout: Duration) -> Result<NodeResponses, RecvTimeoutError> { self.rx().lock().unwrap().recv_timeout(timeout) }
   fn refresh_rep(&mut self);
+  /// Takes the handle of the Node's thread, if it has one, so that the Exchange can join it on shutdown
+  fn take_thread(&mut self) -> Option<JoinHandle<()>> { None }
 }
\ No newline at end of file
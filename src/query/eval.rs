@@ -0,0 +1,167 @@
+use serde_json::Value;
+use crate::model::sedaroml::{referenced_ids, Block, Model};
+use crate::model::units::Unit;
+use crate::translations::TranslationError;
+use super::{Arrow, Base, Path, Statement};
+
+/// A block of a Model, identified by ID, or its root
+#[derive(Debug, Clone, PartialEq)]
+enum Location {
+  Root,
+  Block(String),
+}
+
+impl Location {
+  fn name(&self) -> String {
+    match self {
+      Location::Root => "root".into(),
+      Location::Block(id) => id.clone(),
+    }
+  }
+  fn block<'a>(&self, model: &'a Model) -> Result<&'a Block, TranslationError> {
+    match self {
+      Location::Root => Ok(&model.root),
+      Location::Block(id) => Ok(model.block_by_id(id)?),
+    }
+  }
+  fn block_mut<'a>(&self, model: &'a mut Model) -> Result<&'a mut Block, TranslationError> {
+    match self {
+      Location::Root => Ok(&mut model.root),
+      Location::Block(id) => Ok(model.block_by_id_mut(id)?),
+    }
+  }
+}
+
+/// Resolves the blocks holding the path's field
+fn locate(model: &Model, path: &Path) -> Result<Vec<Location>, TranslationError> {
+  let mut locations = match &path.base {
+    Base::Block(filter) => {
+      let id = model.blocks.iter().find(
+        |(_, block)| filter.iter().all(|(k, v)| block.get(k) == Some(v))
+      ).map(|(id, _)| id.clone());
+      match id {
+        Some(id) => vec![Location::Block(id)],
+        None => return Err(TranslationError::BlockNotFound(format!("No block matches `{}`", path.base))),
+      }
+    },
+    Base::Root => vec![Location::Root],
+    Base::Type(block_type) => model.block_ids_of_type(block_type)
+      .map_err(|_| TranslationError::BlockTypeNotFound(block_type.clone()))?
+      .into_iter().map(Location::Block).collect(),
+  };
+  for relationship in &path.relationships {
+    let mut next = vec![];
+    for location in &locations {
+      let ids = match location {
+        Location::Block(id) => model.related_ids(id, relationship)?,
+        Location::Root => {
+          let missing = || TranslationError::MissingField { block: location.name(), field: relationship.clone() };
+          referenced_ids(model.root.get(relationship).ok_or_else(missing)?).ok_or_else(
+            || TranslationError::TypeMismatch { block: location.name(), field: relationship.clone(), expected: "a relationship".into() }
+          )?
+        },
+      };
+      for id in ids {
+        model.block_by_id(&id)?;
+        next.push(Location::Block(id));
+      }
+    }
+    locations = next;
+  }
+  Ok(locations)
+}
+
+fn number(location: &Location, field: &str, value: &Value) -> Result<f64, TranslationError> {
+  value.as_f64().ok_or_else(
+    || TranslationError::TypeMismatch { block: location.name(), field: field.to_string(), expected: "a number".into() }
+  )
+}
+
+/// Converts a result back to a JSON number, keeping integers as integers where the inputs were integers
+fn to_value(result: f64, integer: bool) -> Value {
+  if integer && result.fract() == 0.0 && result.abs() < i64::MAX as f64 {
+    Value::from(result as i64)
+  } else {
+    Value::from(result)
+  }
+}
+
+/// The unit of the path's field at a location: the unit given with `as` or else the one declared by the Model
+fn unit(model: &Model, location: &Location, path: &Path) -> Result<Option<Unit>, TranslationError> {
+  match path.unit {
+    Some(unit) => Ok(Some(unit)),
+    None => Ok(model.declared_unit(location.block(model)?, &path.field)?),
+  }
+}
+
+/// Converts a value between units where both are known, copying it as-is otherwise
+fn convert(location: &Location, field: &str, value: &Value, from: Option<Unit>, to: Option<Unit>) -> Result<Value, TranslationError> {
+  match (from, to) {
+    (Some(from), Some(to)) if from != to => {
+      let result = from.convert(number(location, field, value)?, &to)?;
+      Ok(to_value(result, value.is_i64()))
+    },
+    _ => Ok(value.clone()),
+  }
+}
+
+pub fn apply(statement: &Statement, from: &Model, to: &mut Model) -> Result<(), TranslationError> {
+  let sources = locate(from, &statement.source)?;
+  let field = &statement.source.field;
+  let values = sources.iter().map(|location| {
+    location.block(from)?.get(field).cloned().ok_or_else(
+      || TranslationError::MissingField { block: location.name(), field: field.clone() }
+    )
+  }).collect::<Result<Vec<_>, _>>()?;
+  let source_units = sources.iter().map(|location| unit(from, location, &statement.source)).collect::<Result<Vec<_>, _>>()?;
+  let targets = locate(to, &statement.target)?;
+  let target_units = targets.iter().map(|location| unit(to, location, &statement.target)).collect::<Result<Vec<_>, _>>()?;
+
+  let outputs = match statement.arrow {
+    Arrow::Copy => {
+      if values.len() != targets.len() {
+        return Err(TranslationError::Custom(format!(
+          "`{}` has {} value(s) but `{}` has {} field(s)", statement.source, values.len(), statement.target, targets.len()
+        )));
+      }
+      let mut outputs = vec![];
+      for (i, value) in values.iter().enumerate() {
+        outputs.push(convert(&sources[i], field, value, source_units[i], target_units[i])?);
+      }
+      outputs
+    },
+    Arrow::FanOut => {
+      let [value] = &values[..] else {
+        return Err(TranslationError::Custom(format!("`{}` has {} values but `-*>` needs one", statement.source, values.len())));
+      };
+      let mut outputs = vec![];
+      for target_unit in &target_units {
+        let converted = convert(&sources[0], field, value, source_units[0], *target_unit)?;
+        let result = number(&sources[0], field, &converted)? * targets.len() as f64;
+        outputs.push(to_value(result, converted.is_i64()));
+      }
+      outputs
+    },
+    Arrow::FanIn => {
+      if targets.len() != 1 || values.is_empty() {
+        return Err(TranslationError::Custom(format!(
+          "`/->` needs one or more values and one field, but `{}` has {} value(s) and `{}` has {} field(s)",
+          statement.source, values.len(), statement.target, targets.len()
+        )));
+      }
+      let (mut sum, mut integer) = (0.0, true);
+      for (i, value) in values.iter().enumerate() {
+        let converted = convert(&sources[i], field, value, source_units[i], target_units[0])?;
+        sum += number(&sources[i], field, &converted)?;
+        integer &= converted.is_i64();
+      }
+      let n = values.len() as f64;
+      vec![to_value(sum / n / n, integer)]
+    },
+  };
+
+  for (location, value) in targets.iter().zip(outputs) {
+    location.block_mut(to)?.insert(statement.target.field.clone(), value);
+  }
+  Ok(())
+}
@@ -0,0 +1,345 @@
+//! A subset of SedaroQL for defining Translations as queries instead of code.
+//!
+//! A query is a list of statements, separated by commas or newlines, each mapping a source path in the `from` Model to
+//! a target path in the `to` Model:
+//!
+//! ```text
+//! block!(name='spacecraft_dry_mass').value -> Spacecraft.dryMass,
+//! block!(name='spacecraft_battery_esr').value -*> Spacecraft.batteryPacks.esr
+//! ```
+//!
+//! A path starts at the first block matching a filter (`block!(key=value, ...)`), at the Model's `root`, or at every
+//! block of a type (`Type`, resolved through the Model's `index`).  Every segment but the last dereferences a
+//! relationship and the last names the field.  The arrows are:
+//!
+//! - `->` copies each source value to the corresponding target field
+//! - `-*>` fans a single value out to every target field, multiplied by the number of target fields
+//! - `/->` fans values in to a single target field as their mean divided by the number of source values
+//!
+//! Queries are reversed automatically (`->` with `->`, `-*>` with `/->`).  Queries that can't be reversed fail to
+//! compile and need an explicit reverse query.
+
+mod eval;
+mod parser;
+
+use std::fmt;
+use serde_json::Value;
+use crate::model::sedaroml::Model;
+use crate::model::units::{Unit, UnitError};
+use crate::translations::{Operation, TranslationError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+  /// The query couldn't be parsed.  `position` is the byte offset of the error in the query.
+  Syntax { position: usize, message: String },
+  /// A statement can't be executed as written
+  Invalid { statement: String, message: String },
+  /// A statement can't be reversed automatically
+  NotInvertible { statement: String, message: String },
+}
+
+impl fmt::Display for QueryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      QueryError::Syntax { position, message } => write!(f, "Syntax error at {}: {}", position, message),
+      QueryError::Invalid { statement, message } => write!(f, "Invalid statement `{}`: {}", statement, message),
+      QueryError::NotInvertible { statement, message } => write!(f, "Statement `{}` is not invertible: {}", statement, message),
+    }
+  }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Where a path starts
+#[derive(Debug, Clone, PartialEq)]
+pub enum Base {
+  /// The first block whose fields hold all of the given values
+  Block(Vec<(String, Value)>),
+  Root,
+  /// Every block of the type
+  Type(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+  pub base: Base,
+  /// Relationships dereferenced from the base, in order
+  pub relationships: Vec<String>,
+  pub field: String,
+  /// Unit of the field given with `as`, overriding the unit declared by the Model
+  pub unit: Option<Unit>,
+}
+
+impl Path {
+  /// Whether the path always resolves to exactly one field
+  pub fn is_single(&self) -> bool {
+    !matches!(self.base, Base::Type(_)) && self.relationships.is_empty()
+  }
+  /// Whether writing to the path would change which block its base selects
+  fn writes_own_filter(&self) -> bool {
+    match &self.base {
+      Base::Block(filter) => self.relationships.is_empty() && filter.iter().any(|(k, _)| *k == self.field),
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrow {
+  /// `->`
+  Copy,
+  /// `-*>`
+  FanOut,
+  /// `/->`
+  FanIn,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+  pub source: Path,
+  pub arrow: Arrow,
+  pub target: Path,
+}
+
+impl Statement {
+  fn validate(&self) -> Result<(), String> {
+    if self.arrow == Arrow::FanOut && !self.source.is_single() {
+      return Err("the source of `-*>` must be a single value".into());
+    }
+    if self.arrow == Arrow::FanIn && !self.target.is_single() {
+      return Err("the target of `/->` must be a single value".into());
+    }
+    if let (Some(from), Some(to)) = (&self.source.unit, &self.target.unit) {
+      if from.kind != to.kind {
+        return Err(UnitError::Incompatible { from: from.to_string(), to: to.to_string() }.to_string());
+      }
+    }
+    if self.target.writes_own_filter() {
+      return Err(format!("writing `{}` would change which block `{}` selects", self.target.field, self.target.base));
+    }
+    Ok(())
+  }
+
+  pub fn reverse(&self) -> Result<Statement, QueryError> {
+    let arrow = match self.arrow {
+      Arrow::Copy => Arrow::Copy,
+      Arrow::FanOut => Arrow::FanIn,
+      Arrow::FanIn => Arrow::FanOut,
+    };
+    let reversed = Statement { source: self.target.clone(), arrow, target: self.source.clone() };
+    reversed.validate().map_err(|message| QueryError::NotInvertible { statement: self.to_string(), message })?;
+    Ok(reversed)
+  }
+
+  pub fn apply(&self, from: &Model, to: &mut Model) -> Result<(), TranslationError> {
+    eval::apply(self, from, to)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+  pub statements: Vec<Statement>,
+}
+
+impl Query {
+  pub fn parse(src: &str) -> Result<Query, QueryError> {
+    let query = parser::parse(src)?;
+    for statement in &query.statements {
+      statement.validate().map_err(|message| QueryError::Invalid { statement: statement.to_string(), message })?;
+    }
+    Ok(query)
+  }
+
+  /// The query mapping the targets back to the sources, with statements in reverse order
+  pub fn reverse(&self) -> Result<Query, QueryError> {
+    let statements = self.statements.iter().rev().map(|s| s.reverse()).collect::<Result<Vec<_>, _>>()?;
+    Ok(Query { statements })
+  }
+
+  pub fn apply(&self, from: &Model, to: &mut Model) -> Result<(), TranslationError> {
+    self.statements.iter().try_for_each(|s| s.apply(from, to))
+  }
+}
+
+/// Compiles a query into an Operation whose reverse is derived from the query
+pub fn compile(src: &str) -> Result<Operation, QueryError> {
+  let forward = Query::parse(src)?;
+  let reverse = forward.reverse()?;
+  Ok(into_operation(forward, reverse))
+}
+
+/// Compiles a query and an explicit reverse query into an Operation, for queries that can't be reversed automatically
+pub fn compile_with_reverse(forward: &str, reverse: &str) -> Result<Operation, QueryError> {
+  Ok(into_operation(Query::parse(forward)?, Query::parse(reverse)?))
+}
+
+fn into_operation(forward: Query, reverse: Query) -> Operation {
+  Operation::new(None, move |from, to| forward.apply(from, to), move |from, to| reverse.apply(from, to))
+}
+
+fn fmt_value(value: &Value) -> String {
+  match value {
+    Value::String(s) => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+    _ => value.to_string(),
+  }
+}
+
+impl fmt::Display for Base {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Base::Block(filter) => {
+        let filter = filter.iter().map(|(k, v)| format!("{}={}", k, fmt_value(v))).collect::<Vec<_>>();
+        write!(f, "block!({})", filter.join(", "))
+      },
+      Base::Root => write!(f, "root"),
+      Base::Type(block_type) => write!(f, "{}", block_type),
+    }
+  }
+}
+
+impl fmt::Display for Path {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.base)?;
+    for relationship in &self.relationships {
+      write!(f, ".{}", relationship)?;
+    }
+    write!(f, ".{}", self.field)?;
+    match self.unit {
+      Some(unit) if unit.symbol.chars().all(|c| c.is_alphanumeric() || c == '_') => write!(f, " as {}", unit.spec()),
+      Some(unit) => write!(f, " as {}", fmt_value(&Value::String(unit.spec()))),
+      None => Ok(()),
+    }
+  }
+}
+
+impl fmt::Display for Arrow {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Arrow::Copy => write!(f, "->"),
+      Arrow::FanOut => write!(f, "-*>"),
+      Arrow::FanIn => write!(f, "/->"),
+    }
+  }
+}
+
+impl fmt::Display for Statement {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} {} {}", self.source, self.arrow, self.target)
+  }
+}
+
+impl fmt::Display for Query {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let statements = self.statements.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    write!(f, "{}", statements.join(",\n"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value};
+  use crate::model::sedaroml::Model;
+  use crate::translations::TranslationError;
+  use super::{compile, compile_with_reverse, Query, QueryError};
+
+  /// Models `A` and `B` from the README
+  fn models() -> (Model, Model) {
+    let a: Model = serde_json::from_value(json!({
+      "blocks": {
+        "m": {"name": "spacecraft_dry_mass", "value": 100},
+        "e": {"name": "spacecraft_battery_esr", "value": 0.02},
+      },
+      "index": {},
+    })).unwrap();
+    let b: Model = serde_json::from_value(json!({
+      "blocks": {
+        "sc": {"type": "Spacecraft", "dryMass": 0, "batteryPacks": ["bp0", "bp1"]},
+        "bp0": {"type": "BatteryPack", "esr": 0.0},
+        "bp1": {"type": "BatteryPack", "esr": 0.0},
+      },
+      "index": {"Spacecraft": ["sc"], "BatteryPack": ["bp0", "bp1"]},
+    })).unwrap();
+    (a, b)
+  }
+
+  fn field(model: &Model, id: &str, field: &str) -> Value {
+    model.blocks.get(id).unwrap().get(field).unwrap().clone()
+  }
+
+  #[test]
+  fn test_parse_and_reverse() {
+    let query = Query::parse("
+      # A -> B
+      block!(name='spacecraft_dry_mass').value -> Spacecraft.dryMass,
+      block!(name='spacecraft_battery_esr').value -*> Spacecraft.batteryPacks.esr
+    ").unwrap();
+    assert_eq!(query.statements.len(), 2);
+    assert_eq!(query.reverse().unwrap().to_string(), [
+      "Spacecraft.batteryPacks.esr /-> block!(name='spacecraft_battery_esr').value",
+      "Spacecraft.dryMass -> block!(name='spacecraft_dry_mass').value",
+    ].join(",\n"));
+    assert_eq!(Query::parse(&query.to_string()).unwrap(), query);
+
+    assert!(matches!(Query::parse("block!(name='x').value -> "), Err(QueryError::Syntax { .. })));
+    assert_eq!(
+      Query::parse("block!(name='x' .value -> root.y"),
+      Err(QueryError::Syntax { position: 16, message: "expected `,` or `)`".into() }),
+    );
+    assert!(matches!(Query::parse("Spacecraft.batteryPacks.esr -*> root.esr"), Err(QueryError::Invalid { .. })));
+    assert_eq!(
+      Query::parse("block!(name='x').name -> Spacecraft.label").unwrap().reverse().unwrap_err().to_string(),
+      "Statement `block!(name='x').name -> Spacecraft.label` is not invertible: writing `name` would change which block `block!(name='x')` selects",
+    );
+  }
+
+  #[test]
+  fn test_compiled_operation() {
+    let (mut a, mut b) = models();
+    let op = compile("
+      block!(name='spacecraft_dry_mass').value -> Spacecraft.dryMass,
+      block!(name='spacecraft_battery_esr').value -*> Spacecraft.batteryPacks.esr
+    ").unwrap();
+    (op.forward)(&a, &mut b).unwrap();
+    assert_eq!(field(&b, "sc", "dryMass"), json!(100));
+    assert_eq!(field(&b, "bp0", "esr"), json!(0.04));
+    assert_eq!(field(&b, "bp1", "esr"), json!(0.04));
+
+    b.blocks.get_mut("bp0").unwrap().insert("esr".into(), json!(0.08));
+    b.blocks.get_mut("sc").unwrap().insert("dryMass".into(), json!(120));
+    (op.reverse)(&b, &mut a).unwrap();
+    assert_eq!(field(&a, "e", "value"), json!(0.03));
+    assert_eq!(field(&a, "m", "value"), json!(120));
+  }
+
+  #[test]
+  fn test_unit_conversion() {
+    let (mut a, mut b) = models();
+    b.metadata.quantity_kinds.insert("Spacecraft".into(), FromIterator::from_iter([("dryMass".into(), "Mass.kg".into())]));
+    let op = compile("block!(name='spacecraft_dry_mass').value as Mass.g -> Spacecraft.dryMass").unwrap();
+    a.blocks.get_mut("m").unwrap().insert("value".into(), json!(100000));
+    (op.forward)(&a, &mut b).unwrap();
+    assert_eq!(field(&b, "sc", "dryMass"), json!(100));
+    b.blocks.get_mut("sc").unwrap().insert("dryMass".into(), json!(0.25));
+    (op.reverse)(&b, &mut a).unwrap();
+    assert_eq!(field(&a, "m", "value"), json!(250.0));
+
+    let query = Query::parse("root.speed as 'Velocity.km/s' -> root.speed as Velocity").unwrap();
+    assert_eq!(Query::parse(&query.to_string()).unwrap(), query);
+    assert!(matches!(Query::parse("root.mass as Mass.g -> root.length as Length.m"), Err(QueryError::Invalid { .. })));
+    assert!(matches!(Query::parse("root.mass as Mass.m -> root.mass"), Err(QueryError::Syntax { .. })));
+  }
+
+  #[test]
+  fn test_evaluation_errors() {
+    let (a, mut b) = models();
+    let op = compile("block!(name='spacecraft_dry_mass').value -> BatteryPack.esr").unwrap();
+    assert_eq!(
+      (op.forward)(&a, &mut b).unwrap_err(),
+      TranslationError::Custom("`block!(name='spacecraft_dry_mass').value` has 1 value(s) but `BatteryPack.esr` has 2 field(s)".into()),
+    );
+    let op = compile("block!(name='missing').value -> root.x").unwrap();
+    assert!(matches!((op.forward)(&a, &mut b), Err(TranslationError::BlockNotFound(_))));
+    let op = compile_with_reverse("block!(name='spacecraft_dry_mass').name -*> BatteryPack.esr", "root.y -> root.z").unwrap();
+    assert!(matches!((op.forward)(&a, &mut b), Err(TranslationError::TypeMismatch { .. })));
+  }
+}
@@ -0,0 +1,219 @@
+use serde_json::Value;
+use crate::model::units::Unit;
+use super::{Arrow, Base, Path, Query, QueryError, Statement};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  /// `block!`
+  BlockMacro,
+  LParen,
+  RParen,
+  Eq,
+  Comma,
+  Dot,
+  Newline,
+  Literal(Value),
+  Arrow(Arrow),
+}
+
+fn syntax_error(position: usize, message: &str) -> QueryError {
+  QueryError::Syntax { position, message: message.to_string() }
+}
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+  let chars = src.char_indices().collect::<Vec<_>>();
+  let mut tokens = vec![];
+  let mut i = 0;
+  let peek = |i: usize| chars.get(i).map(|(_, c)| *c);
+  while let Some(&(position, c)) = chars.get(i) {
+    let token = match c {
+      '\n' => Token::Newline,
+      c if c.is_whitespace() => { i += 1; continue; },
+      '#' => {
+        while peek(i).is_some_and(|c| c != '\n') { i += 1; }
+        continue;
+      },
+      '(' => Token::LParen,
+      ')' => Token::RParen,
+      '=' => Token::Eq,
+      ',' => Token::Comma,
+      '.' => Token::Dot,
+      '-' if peek(i + 1) == Some('>') => { i += 1; Token::Arrow(Arrow::Copy) },
+      '-' if peek(i + 1) == Some('*') && peek(i + 2) == Some('>') => { i += 2; Token::Arrow(Arrow::FanOut) },
+      '/' if peek(i + 1) == Some('-') && peek(i + 2) == Some('>') => { i += 2; Token::Arrow(Arrow::FanIn) },
+      '\'' | '"' => {
+        let mut s = String::new();
+        i += 1;
+        loop {
+          match peek(i) {
+            None => return Err(syntax_error(position, "unterminated string")),
+            Some('\\') => { s.extend(peek(i + 1)); i += 2; },
+            Some(q) if q == c => break,
+            Some(other) => { s.push(other); i += 1; },
+          }
+        }
+        Token::Literal(Value::String(s))
+      },
+      c if c == '-' || c.is_ascii_digit() => {
+        let start = i;
+        i += 1;
+        while peek(i).is_some_and(|c| c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-') { i += 1; }
+        let end = chars.get(i).map(|(p, _)| *p).unwrap_or(src.len());
+        let number = serde_json::from_str::<serde_json::Number>(&src[chars[start].0..end])
+          .map_err(|_| syntax_error(position, "invalid number"))?;
+        tokens.push((position, Token::Literal(Value::Number(number))));
+        continue;
+      },
+      c if c.is_alphabetic() || c == '_' => {
+        let mut ident = String::new();
+        while let Some(c) = peek(i).filter(|c| c.is_alphanumeric() || *c == '_') { ident.push(c); i += 1; }
+        let token = match ident.as_str() {
+          "block" if peek(i) == Some('!') => { i += 1; Token::BlockMacro },
+          "true" => Token::Literal(Value::Bool(true)),
+          "false" => Token::Literal(Value::Bool(false)),
+          "null" => Token::Literal(Value::Null),
+          _ => Token::Ident(ident),
+        };
+        tokens.push((position, token));
+        continue;
+      },
+      _ => return Err(syntax_error(position, &format!("unexpected character `{}`", c))),
+    };
+    tokens.push((position, token));
+    i += 1;
+  }
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<(usize, Token)>,
+  pos: usize,
+  end: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos).map(|(_, t)| t)
+  }
+  fn position(&self) -> usize {
+    self.tokens.get(self.pos).map(|(p, _)| *p).unwrap_or(self.end)
+  }
+  fn next(&mut self) -> Option<Token> {
+    let token = self.peek().cloned();
+    self.pos += 1;
+    token
+  }
+  fn expect(&mut self, expected: Token, message: &str) -> Result<(), QueryError> {
+    if self.peek() == Some(&expected) {
+      self.pos += 1;
+      Ok(())
+    } else {
+      Err(syntax_error(self.position(), message))
+    }
+  }
+  fn ident(&mut self, message: &str) -> Result<String, QueryError> {
+    match self.peek() {
+      Some(Token::Ident(ident)) => {
+        let ident = ident.clone();
+        self.pos += 1;
+        Ok(ident)
+      },
+      _ => Err(syntax_error(self.position(), message)),
+    }
+  }
+  fn skip_separators(&mut self) -> bool {
+    let start = self.pos;
+    while matches!(self.peek(), Some(Token::Comma | Token::Newline)) { self.pos += 1; }
+    self.pos > start
+  }
+
+  fn query(&mut self) -> Result<Query, QueryError> {
+    let mut statements = vec![];
+    self.skip_separators();
+    while self.peek().is_some() {
+      statements.push(self.statement()?);
+      if !self.skip_separators() && self.peek().is_some() {
+        return Err(syntax_error(self.position(), "expected `,` or a new line between statements"));
+      }
+    }
+    if statements.is_empty() {
+      return Err(syntax_error(self.end, "empty query"));
+    }
+    Ok(Query { statements })
+  }
+
+  fn statement(&mut self) -> Result<Statement, QueryError> {
+    let source = self.path()?;
+    let position = self.position();
+    let arrow = match self.next() {
+      Some(Token::Arrow(arrow)) => arrow,
+      _ => return Err(syntax_error(position, "expected `->`, `-*>` or `/->`")),
+    };
+    let target = self.path()?;
+    Ok(Statement { source, arrow, target })
+  }
+
+  fn path(&mut self) -> Result<Path, QueryError> {
+    let position = self.position();
+    let base = match self.next() {
+      Some(Token::BlockMacro) => {
+        self.expect(Token::LParen, "expected `(`")?;
+        let mut filter = vec![];
+        loop {
+          let key = self.ident("expected a field name")?;
+          self.expect(Token::Eq, "expected `=`")?;
+          let position = self.position();
+          let Some(Token::Literal(value)) = self.next() else {
+            return Err(syntax_error(position, "expected a string, number, boolean or null"));
+          };
+          filter.push((key, value));
+          match self.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RParen) => break,
+            _ => { self.pos -= 1; return Err(syntax_error(self.position(), "expected `,` or `)`")); },
+          }
+        }
+        Base::Block(filter)
+      },
+      Some(Token::Ident(ident)) if ident == "root" => Base::Root,
+      Some(Token::Ident(ident)) => Base::Type(ident),
+      _ => return Err(syntax_error(position, "expected `block!(...)`, `root` or a block type")),
+    };
+    let mut segments = vec![];
+    while self.peek() == Some(&Token::Dot) {
+      self.pos += 1;
+      segments.push(self.ident("expected a field name")?);
+    }
+    let Some(field) = segments.pop() else {
+      return Err(syntax_error(self.position(), "expected `.` and a field name"));
+    };
+    let unit = match self.peek() {
+      Some(Token::Ident(ident)) if ident == "as" => {
+        self.pos += 1;
+        Some(self.unit()?)
+      },
+      _ => None,
+    };
+    Ok(Path { base, relationships: segments, field, unit })
+  }
+
+  /// Parses a unit given as `Kind`, `Kind.unit` or, for units that aren't identifiers, `'Kind.unit'`
+  fn unit(&mut self) -> Result<Unit, QueryError> {
+    let position = self.position();
+    let spec = match self.next() {
+      Some(Token::Literal(Value::String(spec))) => spec,
+      Some(Token::Ident(kind)) if self.peek() == Some(&Token::Dot) => {
+        self.pos += 1;
+        format!("{}.{}", kind, self.ident("expected a unit")?)
+      },
+      Some(Token::Ident(kind)) => kind,
+      _ => return Err(syntax_error(position, "expected a quantity kind")),
+    };
+    Unit::from_spec(&spec).map_err(|e| syntax_error(position, &e.to_string()))
+  }
+}
+
+pub fn parse(src: &str) -> Result<Query, QueryError> {
+  Parser { tokens: tokenize(src)?, pos: 0, end: src.len() }.query()
+}
@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use tokio::sync::watch;
+use crate::commands::ConflictResolutions;
+use crate::exchange::ExchangeError;
+use crate::translations::TranslationError;
+use crate::model::merge::MergeConflict;
+use crate::model::sedaroml::ModelDiff;
+
+/// Number of completed round reports retained by a `RoundLock`
+const MAX_REPORTS: usize = 100;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OperationDirection {
+  Forward,
+  Reverse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OperationOutcome {
+  /// The Operation changed the `to` representation
+  Changed,
+  /// The Operation ran but left the `to` representation as it was
+  Unchanged,
+  /// The Operation returned an error.  Any changes it made to the `to` representation were discarded.
+  Failed(TranslationError),
+}
+
+/// Result of a single Operation executed during a translation round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationReport {
+  pub name: Option<String>,
+  pub direction: OperationDirection,
+  pub outcome: OperationOutcome,
+}
+
+/// Result of executing all Operations of a Translation from one Node into another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationReport {
+  pub from: String,
+  pub to: String,
+  pub operations: Vec<OperationReport>,
+  /// The diff sent to the `to` Node, or `None` if the Translation left its representation unchanged
+  pub diff: Option<ModelDiff>,
+}
+
+/// A change to a Node's representation on disk that the Exchange found when about to overwrite it with a translation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictReport {
+  pub node: String,
+  /// Diff from the representation on disk to the translated representation
+  pub diff: ModelDiff,
+  pub resolution: ConflictResolutions,
+  /// Changes that a `Merge` resolution couldn't combine, for which the edit on disk was kept
+  pub merge_conflicts: Vec<MergeConflict>,
+}
+
+/// Time taken by a Node to complete its side-effects after being `Changed` by a translation round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SideEffectReport {
+  pub node: String,
+  #[serde(with = "duration_secs")]
+  pub elapsed: Duration,
+}
+
+/// Summary of a completed translation round.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundReport {
+  /// Sequence number of the round, starting at 1
+  pub round: u64,
+  /// Identifier of the Node whose change started the round
+  pub trigger: String,
+  /// Translations executed during the round, in execution order
+  pub translations: Vec<TranslationReport>,
+  /// Concurrent edits detected during the round and how they were resolved
+  pub conflicts: Vec<ConflictReport>,
+  /// Whether the round's changes were approved, or `None` if approval wasn't required
+  pub approved: Option<bool>,
+  /// Whether a failure aborted the round.  A failed Operation aborts it before any Node's representation is changed; a
+  /// failure to read or write a Node's file aborts the rest of the commit, leaving the Nodes already written changed.
+  pub aborted: bool,
+  /// The failures that aborted the round or, with `FailurePolicy::SkipOperation`, discarded a Node's translation
+  pub errors: Vec<String>,
+  pub side_effects: Vec<SideEffectReport>,
+  /// Time from the start of the round to all Node side-effects completing
+  #[serde(with = "duration_secs")]
+  pub elapsed: Duration,
+}
+
+impl RoundReport {
+  pub fn new(trigger: String) -> RoundReport {
+    RoundReport {
+      round: 0,
+      trigger,
+      translations: vec![],
+      conflicts: vec![],
+      approved: None,
+      aborted: false,
+      errors: vec![],
+      side_effects: vec![],
+      elapsed: Duration::ZERO,
+    }
+  }
+  /// Identifiers of the Nodes whose representations were changed during the round
+  pub fn changed_nodes(&self) -> impl Iterator<Item = &String> {
+    self.translations.iter().filter(|t| t.diff.is_some()).map(|t| &t.to)
+  }
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).unwrap()
+  }
+}
+
+/// Serializes Durations as fractional seconds
+mod duration_secs {
+  use std::time::Duration;
+  use serde::{Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+  }
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    Ok(Duration::from_secs_f64(f64::deserialize(deserializer)?))
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RoundStatus {
+  in_progress: bool,
+  completed: u64,
+  /// Whether the translation thread has exited, so no further rounds will complete
+  halted: bool,
+}
+
+/// Exchange-wide lock that is held by the translation thread for the duration of each translation round.
+///
+/// Other threads and tasks can wait on the lock to synchronize with the Exchange instead of sleeping.
+#[derive(Clone)]
+pub struct RoundLock {
+  status: Arc<watch::Sender<RoundStatus>>,
+  reports: Arc<Mutex<VecDeque<RoundReport>>>,
+}
+
+impl RoundLock {
+  pub fn new() -> RoundLock {
+    RoundLock {
+      status: Arc::new(watch::Sender::new(RoundStatus::default())),
+      reports: Arc::new(Mutex::new(VecDeque::new())),
+    }
+  }
+
+  /// Marks a translation round as in progress
+  pub(crate) fn acquire(&self) {
+    self.status.send_modify(|status| status.in_progress = true);
+  }
+
+  /// Records the report of the round in progress and wakes everything waiting on it
+  pub(crate) fn release(&self, mut report: RoundReport) -> RoundReport {
+    let mut reports = self.reports.lock().unwrap();
+    report.round = self.completed() + 1;
+    if reports.len() == MAX_REPORTS { reports.pop_front(); }
+    reports.push_back(report.clone());
+    drop(reports);
+    self.status.send_modify(|status| {
+      status.in_progress = false;
+      status.completed = report.round;
+    });
+    report
+  }
+
+  /// Records that the translation thread has exited, normally or not, and wakes everything waiting on it
+  pub(crate) fn halt(&self) {
+    self.status.send_modify(|status| status.halted = true);
+  }
+
+  pub fn is_locked(&self) -> bool {
+    self.status.borrow().in_progress
+  }
+
+  pub fn is_halted(&self) -> bool {
+    self.status.borrow().halted
+  }
+
+  /// Number of translation rounds completed since the Exchange started
+  pub fn completed(&self) -> u64 {
+    self.status.borrow().completed
+  }
+
+  /// Number of translation rounds started since the Exchange started, including the round in progress
+  pub fn started(&self) -> u64 {
+    let status = self.status.borrow();
+    status.completed + status.in_progress as u64
+  }
+
+  pub fn last_report(&self) -> Option<RoundReport> {
+    self.reports.lock().unwrap().back().cloned()
+  }
+
+  /// Reports of the most recently completed rounds, oldest first
+  pub fn reports(&self) -> Vec<RoundReport> {
+    self.reports.lock().unwrap().iter().cloned().collect()
+  }
+
+  /// Blocks until the translation round in progress (if any) is complete and returns the report of the last round.
+  /// Fails with `ExchangeError::Halted` if the translation thread has exited.
+  pub fn wait(&self) -> Result<Option<RoundReport>, ExchangeError> {
+    futures::executor::block_on(self.wait_async())
+  }
+
+  pub async fn wait_async(&self) -> Result<Option<RoundReport>, ExchangeError> {
+    let mut rx = self.status.subscribe();
+    let status = rx.wait_for(|status| !status.in_progress || status.halted).await.expect("Round lock closed").clone();
+    if status.halted {
+      return Err(ExchangeError::Halted);
+    }
+    Ok(self.last_report())
+  }
+
+  /// Blocks until a round numbered after `after` has been triggered by the Node `iden` and returns its report.  Fails
+  /// with `ExchangeError::Halted` if the translation thread exits before completing such a round.
+  pub fn wait_for_change(&self, iden: &str, after: u64) -> Result<RoundReport, ExchangeError> {
+    futures::executor::block_on(self.wait_for_change_async(iden, after))
+  }
+
+  pub async fn wait_for_change_async(&self, iden: &str, after: u64) -> Result<RoundReport, ExchangeError> {
+    let mut rx = self.status.subscribe();
+    let mut seen = after;
+    loop {
+      let status = rx.wait_for(|status| status.completed > seen || status.halted).await.expect("Round lock closed").clone();
+      seen = status.completed;
+      let report = self.reports.lock().unwrap().iter().find(
+        |r| r.round > after && r.trigger == iden
+      ).cloned();
+      if let Some(report) = report {
+        return Ok(report);
+      }
+      if status.halted {
+        return Err(ExchangeError::Halted);
+      }
+    }
+  }
+}
+
+impl Default for RoundLock {
+  fn default() -> Self {
+    Self::new()
+  }
+}
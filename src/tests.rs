@@ -1,66 +1,142 @@
 #[cfg(test)]
 mod tests {
-  use std::thread::sleep;
-  use std::time::Duration;
   use serde_json::Value;
-  use crate::model::sedaroml::{Model, Block};
+  use crate::model::merge::MergeConflict;
+  use crate::model::sedaroml::{DiffOptions, Model, Block, read_model, write_model};
   use crate::nodes::sedaroml::SedaroML;
-  use crate::exchange::Exchange;
-  use crate::translations::{Operation, Translation};
+  use crate::exchange::{Exchange, ExchangeError};
+  use crate::graph::TranslationGraph;
+  use crate::round::{OperationDirection, OperationOutcome};
+  use crate::translations::{BlockSelector, FieldMapping, FieldRef, Operation, Translation, TranslationError};
   use crate::nodes::traits::Exchangeable;
+  use crate::commands::{ConflictResolutions, NodeCommands, NodeResponses};
+  use crate::conflicts::ConflictPolicy;
+  use crate::exchange::{ExchangeOptions, FailurePolicy};
+  use crate::approval::ApprovalMode;
+  use std::collections::HashMap;
+  use std::sync::{mpsc, Arc, Mutex};
+  use std::thread;
+  use std::time::Duration;
+
+  /// Writes a SedaroML file with a single block `i` holding the value `v` and returns its path
+  fn write_fixture(dir: &tempfile::TempDir, name: &str, v: i64) -> String {
+    let filename = dir.path().join(name).to_str().unwrap().to_string();
+    let mut model = Model::new();
+    model.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
+    write_model(&filename, &model).unwrap();
+    filename
+  }
 
+  /// Node that reports a conflict on start and records the resolution it is asked to apply
+  struct ConflictingNode {
+    identifier: String,
+    filename: String,
+    rep: Option<Model>,
+    tx: mpsc::Sender<NodeCommands>,
+    rx: Arc<Mutex<mpsc::Receiver<NodeResponses>>>,
+  }
+
+  impl ConflictingNode {
+    fn new(identifier: &str, filename: String, resolutions: Arc<Mutex<Vec<(String, ConflictResolutions)>>>) -> Arc<Mutex<ConflictingNode>> {
+      let (tx_to_node, rx_in_node) = mpsc::channel::<NodeCommands>();
+      let (tx_to_exchange, rx_in_exchange) = mpsc::channel::<NodeResponses>();
+      let iden = identifier.to_string();
+      let filename_clone = filename.clone();
+      thread::spawn(move || {
+        while let Ok(command) = rx_in_node.recv() {
+          match command {
+            NodeCommands::Start => {
+              let mut foreign = Model::new();
+              foreign.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number((-1).into()))]));
+              let diff = read_model(&filename_clone).unwrap().diff(&foreign);
+              tx_to_exchange.send(NodeResponses::Conflict(diff)).unwrap();
+            },
+            NodeCommands::ResolveConflict(resolution) => {
+              resolutions.lock().unwrap().push((iden.clone(), resolution));
+              tx_to_exchange.send(NodeResponses::ConflictResolved(Duration::ZERO)).unwrap();
+              tx_to_exchange.send(NodeResponses::Started).unwrap();
+            },
+            NodeCommands::Changed(_) => { tx_to_exchange.send(NodeResponses::Done(Duration::ZERO)).unwrap(); },
+            NodeCommands::Stop => {
+              tx_to_exchange.send(NodeResponses::Stopped).unwrap();
+              break;
+            },
+            NodeCommands::Done => {},
+          }
+        }
+      });
+      Arc::new(Mutex::new(ConflictingNode {
+        identifier: identifier.into(),
+        filename,
+        rep: None,
+        tx: tx_to_node,
+        rx: Arc::new(Mutex::new(rx_in_exchange)),
+      }))
+    }
+  }
+
+  impl Exchangeable for ConflictingNode {
+    fn identifier(&self) -> String { self.identifier.clone() }
+    fn sedaroml_filename(&self) -> String { self.filename.clone() }
+    fn rep(&self) -> &Model { self.rep.as_ref().unwrap() }
+    fn rep_mut(&mut self) -> &mut Model { self.rep.as_mut().unwrap() }
+    fn tx(&self) -> &mpsc::Sender<NodeCommands> { &self.tx }
+    fn rx(&self) -> &Arc<Mutex<mpsc::Receiver<NodeResponses>>> { &self.rx }
+    fn refresh_rep(&mut self) { self.rep = Some(read_model(&self.filename).unwrap()); }
+  }
 
   #[test]
   fn test_simple_exchange() {
-    let a = SedaroML::new("a".into(), "a.txt".into());
-    let b = SedaroML::new("b".into(), "b.txt".into());
-    let c = SedaroML::new("c".into(), "c.txt".into());
-    let d = SedaroML::new("d".into(), "d.txt".into());
-    let e = SedaroML::new("e".into(), "e.txt".into());
-    let add_one = Operation {
-      name: Some("+1".into()),
-      forward: |from: &Model, to: &mut Model| {
+    let dir = tempfile::tempdir().unwrap();
+    let a = SedaroML::new("a".into(), write_fixture(&dir, "a.json", 0));
+    let b = SedaroML::new("b".into(), write_fixture(&dir, "b.json", 0));
+    let c = SedaroML::new("c".into(), write_fixture(&dir, "c.json", 0));
+    let d = SedaroML::new("d".into(), write_fixture(&dir, "d.json", 0));
+    let e = SedaroML::new("e".into(), write_fixture(&dir, "e.json", 100));
+    let add_one = Operation::new(
+      Some("+1".into()),
+      |from: &Model, to: &mut Model| {
         let v = from.blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap() + 1;
         to.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
         Ok(())
       },
-      reverse: |from: &Model, to: &mut Model| {
+      |from: &Model, to: &mut Model| {
         let v = from.blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap() - 1;
         to.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
         Ok(())
       },
-    };
-    let multiply_two = Operation {
-      name: Some("*2".into()),
-      forward: |from: &Model, to: &mut Model| {
+    );
+    let multiply_two = Operation::new(
+      Some("*2".into()),
+      |from: &Model, to: &mut Model| {
         let v = from.blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap() * 2;
         to.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
         Ok(())
       },
-      reverse: |from: &Model, to: &mut Model| {
+      |from: &Model, to: &mut Model| {
         let v = from.blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap() / 2;
         to.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
         Ok(())
       },
-    };
-    let multiply_ten = Operation {
-      name: Some("*10".into()),
-      forward: |from: &Model, to: &mut Model| {
+    );
+    let multiply_ten = Operation::new(
+      Some("*10".into()),
+      |from: &Model, to: &mut Model| {
         let v = from.blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap() * 10;
         to.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
         Ok(())
       },
-      reverse: |from: &Model, to: &mut Model| {
+      |from: &Model, to: &mut Model| {
         let v = from.blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap() / 10;
         to.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(v.into()))]));
         Ok(())
       },
-    };
-    let noop = Operation {
-      name: Some("noop".into()),
-      forward: |_, _| { Ok(()) },
-      reverse: |_, _| { Ok(()) },
-    };
+    );
+    let noop = Operation::new(
+      Some("noop".into()),
+      |_, _| { Ok(()) },
+      |_, _| { Ok(()) },
+    );
     let t_a = Translation {
       from: a.clone(),
       to: b.clone(),
@@ -82,19 +158,34 @@ mod tests {
       to: d.clone(),
       operations: vec![noop, multiply_two_clone],
     };
-    let exchange = Exchange::new(vec![t_a, t_b, t_c, t_d]);
-  
-    exchange.trigger_watch_for_model("e".into());
-  
-    // println!("A: {:?}", models.get("a").unwrap().lock().unwrap().rep());
-    // println!("B: {:?}", models.get("b").unwrap().lock().unwrap().rep());
-    // println!("C: {:?}", models.get("c").unwrap().lock().unwrap().rep());
-    // println!("D: {:?}", models.get("d").unwrap().lock().unwrap().rep());
-    // println!("E: {:?}", models.get("e").unwrap().lock().unwrap().rep());
+    let exchange = Exchange::new(vec![t_a, t_b, t_c, t_d]).unwrap();
   
-    // TODO: Eventually, wait on the translation round lock before running the tests
-    sleep(Duration::from_millis(1000));
-    println!("Starting testing...");
+    let report = exchange.trigger_and_wait("e").unwrap();
+    assert_eq!(report.round, 1);
+    assert_eq!(report.trigger, "e");
+    // Breadth-first from `e`, in registration order
+    assert_eq!(
+      report.translations.iter().map(|t| (t.from.as_str(), t.to.as_str())).collect::<Vec<_>>(),
+      vec![("e", "b"), ("b", "a"), ("b", "c"), ("c", "d")],
+    );
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["b", "a", "c", "d"]);
+    let e_to_b = &report.translations[0];
+    assert_eq!(e_to_b.operations.len(), 1);
+    assert_eq!(e_to_b.operations[0].name, Some("*10".into()));
+    assert_eq!(e_to_b.operations[0].direction, OperationDirection::Reverse);
+    assert_eq!(e_to_b.operations[0].outcome, OperationOutcome::Changed);
+    let b_diff = e_to_b.diff.as_ref().unwrap();
+    assert_eq!(b_diff.updated_blocks.get("i").unwrap().updated_fields.get("v").unwrap().new_value, Value::Number(10.into()));
+    let c_to_d = &report.translations[3];
+    assert_eq!(c_to_d.operations.iter().map(|o| o.outcome.clone()).collect::<Vec<_>>(), vec![OperationOutcome::Unchanged, OperationOutcome::Changed]);
+    assert_eq!(report.side_effects.len(), 4);
+    let json: Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["trigger"], "e");
+    assert!(json["elapsed"].is_f64());
+    assert_eq!(exchange.last_round_report().unwrap().round, 1);
+    assert!(!exchange.round_lock().is_locked());
+    assert_eq!(exchange.trigger_and_wait("f").err(), Some(ExchangeError::UnknownNode("f".into())));
+
     assert!(*a.lock().unwrap().rep().blocks.get("i").unwrap() == Block::from_iter([("v".into(), Value::Number(9.into()))]));
     assert!(*b.lock().unwrap().rep().blocks.get("i").unwrap() == Block::from_iter([("v".into(), Value::Number(10.into()))]));
     assert!(*c.lock().unwrap().rep().blocks.get("i").unwrap() == Block::from_iter([("v".into(), Value::Number(20.into()))]));
@@ -104,39 +195,666 @@ mod tests {
   }
 
   #[test]
-  #[should_panic(expected = "`a` == `a`")]
+  fn test_exchange_disconnected_subgraphs() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = SedaroML::new("a".into(), write_fixture(&dir, "a.json", 1));
+    let b = SedaroML::new("b".into(), write_fixture(&dir, "b.json", 0));
+    let c = SedaroML::new("c".into(), write_fixture(&dir, "c.json", 2));
+    let d = SedaroML::new("d".into(), write_fixture(&dir, "d.json", 0));
+    let copy = Operation::new(
+      None,
+      |from: &Model, to: &mut Model| {
+        to.blocks.insert("i".into(), from.blocks.get("i").unwrap().clone());
+        Ok(())
+      },
+      |from: &Model, to: &mut Model| {
+        to.blocks.insert("i".into(), from.blocks.get("i").unwrap().clone());
+        Ok(())
+      },
+    );
+    let t_a = Translation { from: a, to: b.clone(), operations: vec![copy.clone()] };
+    let t_b = Translation { from: c, to: d.clone(), operations: vec![copy] };
+    let exchange = Exchange::new(vec![t_a, t_b]).unwrap();
+
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["b"]);
+    assert!(*b.lock().unwrap().rep().blocks.get("i").unwrap() == Block::from_iter([("v".into(), Value::Number(1.into()))]));
+    assert!(*d.lock().unwrap().rep().blocks.get("i").unwrap() == Block::from_iter([("v".into(), Value::Number(0.into()))]));
+
+    // The write to `b` is not picked up as a new change
+    let report = exchange.trigger_and_wait("c").unwrap();
+    assert_eq!(report.round, 2);
+    assert_eq!(report.changed_nodes().count(), 1);
+    assert_eq!(exchange.round_reports().len(), 2);
+  }
+
+  /// Operation copying block `i` in both directions
+  fn copy_operation() -> Operation {
+    Operation::new(
+      None,
+      |from: &Model, to: &mut Model| {
+        to.blocks.insert("i".into(), from.block_by_id("i")?.clone());
+        Ok(())
+      },
+      |from: &Model, to: &mut Model| {
+        to.blocks.insert("i".into(), from.block_by_id("i")?.clone());
+        Ok(())
+      },
+    )
+  }
+
+  /// Translation `a -> b` copying block `i` between fixtures holding `a_v` and `b_v`
+  fn copy_translation(dir: &tempfile::TempDir, a_v: i64, b_v: i64) -> Translation {
+    let a = SedaroML::new("a".into(), write_fixture(dir, "a.json", a_v));
+    let b = SedaroML::new("b".into(), write_fixture(dir, "b.json", b_v));
+    Translation { from: a, to: b, operations: vec![copy_operation()] }
+  }
+
+  #[test]
+  fn test_exchange_shutdown() {
+    let dir = tempfile::tempdir().unwrap();
+    let translation = copy_translation(&dir, 1, 0);
+    let (a, b) = (translation.from.clone(), translation.to.clone());
+    let exchange = Exchange::new(vec![translation]).unwrap();
+    let round_lock = exchange.round_lock().clone();
+
+    // Changes queued before shutdown are still translated
+    exchange.trigger_watch_for_model("a".into()).unwrap();
+    assert_eq!(exchange.shutdown(), Ok(()));
+    assert_eq!(round_lock.completed(), 1);
+    assert_eq!(round_lock.last_report().unwrap().changed_nodes().collect::<Vec<_>>(), vec!["b"]);
+
+    // Node threads have exited
+    assert!(a.lock().unwrap().tx().send(NodeCommands::Done).is_err());
+    assert!(b.lock().unwrap().tx().send(NodeCommands::Done).is_err());
+  }
+
+  #[test]
   fn test_exchange_validation_iden_uniqueness() {
     let a = SedaroML::new("a".into(), "a.txt".into());
     let b = SedaroML::new("a".into(), "b.txt".into());
     let t_a = Translation { from: a, to: b, operations: vec![] };
-    Exchange::new(vec![t_a]);
+    assert_eq!(Exchange::new(vec![t_a]).err(), Some(ExchangeError::DuplicateIdentifier("a".into())));
   }
 
   #[test]
-  #[should_panic(expected = "`a` & `a`")]
   fn test_exchange_validation_recursive_nodes() {
     let a = SedaroML::new("a".into(), "a.txt".into());
     let t_a = Translation { from: a.clone(), to: a, operations: vec![] };
-    Exchange::new(vec![t_a]);
+    assert_eq!(Exchange::new(vec![t_a]).err(), Some(ExchangeError::SelfTranslation("a".into())));
   }
 
   #[test]
-  #[should_panic(expected = "Duplicate model identifier detected: `b`")]
   fn test_exchange_validation_same_iden_multiple_nodes() {
     let a = SedaroML::new("a".into(), "a.txt".into());
     let b = SedaroML::new("b".into(), "b.txt".into());
     let c = SedaroML::new("b".into(), "c.txt".into());
     let t_a = Translation { from: a.clone(), to: b, operations: vec![] };
     let t_b = Translation { from: a, to: c, operations: vec![] };
-    Exchange::new(vec![t_a, t_b]);
+    assert_eq!(Exchange::new(vec![t_a, t_b]).err(), Some(ExchangeError::DuplicateIdentifier("b".into())));
   }
 
   #[test]
-  #[should_panic(expected = "Duplicate filename detected: `a.txt`")]
   fn test_exchange_validation_duplicate_filenames() {
     let a = SedaroML::new("a".into(), "a.txt".into());
     let b = SedaroML::new("b".into(), "a.txt".into());
     let t_a = Translation { from: a, to: b, operations: vec![] };
-    Exchange::new(vec![t_a]);
+    assert_eq!(Exchange::new(vec![t_a]).err(), Some(ExchangeError::DuplicateFilename("a.txt".into())));
+  }
+
+  #[test]
+  fn test_exchange_validation_duplicate_pairs() {
+    let a = SedaroML::new("a".into(), "a.txt".into());
+    let b = SedaroML::new("b".into(), "b.txt".into());
+    let t_a = Translation { from: a.clone(), to: b.clone(), operations: vec![] };
+    let t_b = Translation { from: b, to: a, operations: vec![] };
+    assert_eq!(Exchange::new(vec![t_a, t_b]).err(), Some(ExchangeError::DuplicatePair { from: "b".into(), to: "a".into() }));
+  }
+
+  #[test]
+  fn test_exchange_node_start_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_filename = dir.path().join("a.json").to_str().unwrap().to_string();
+    let b_filename = dir.path().join("b.json").to_str().unwrap().to_string();
+    write_model(&a_filename, &Model::new()).unwrap();
+    let a = SedaroML::new("a".into(), a_filename);
+    let b = SedaroML::new("b".into(), b_filename.clone());
+    let t_a = Translation { from: a, to: b, operations: vec![] };
+    assert_eq!(
+      Exchange::new(vec![t_a]).err(),
+      Some(ExchangeError::NodeStartFailed { identifier: "b".into(), filename: b_filename }),
+    );
+  }
+
+  #[test]
+  fn test_exchange_validation_cycle() {
+    let a = SedaroML::new("a".into(), "a.txt".into());
+    let b = SedaroML::new("b".into(), "b.txt".into());
+    let c = SedaroML::new("c".into(), "c.txt".into());
+    let t_a = Translation { from: a.clone(), to: b.clone(), operations: vec![] };
+    let t_b = Translation { from: b, to: c.clone(), operations: vec![] };
+    let t_c = Translation { from: c, to: a, operations: vec![] };
+    assert_eq!(
+      Exchange::new(vec![t_a, t_b, t_c]).err(),
+      Some(ExchangeError::Cycle(vec!["a".into(), "b".into(), "c".into()])),
+    );
+  }
+
+  #[test]
+  fn test_exchange_validation_cycle_off_branch() {
+    let a = SedaroML::new("a".into(), "a.txt".into());
+    let b = SedaroML::new("b".into(), "b.txt".into());
+    let c = SedaroML::new("c".into(), "c.txt".into());
+    let d = SedaroML::new("d".into(), "d.txt".into());
+    let t_a = Translation { from: a, to: b.clone(), operations: vec![] };
+    let t_b = Translation { from: b.clone(), to: c.clone(), operations: vec![] };
+    let t_c = Translation { from: c, to: d.clone(), operations: vec![] };
+    let t_d = Translation { from: d, to: b, operations: vec![] };
+    let err = Exchange::new(vec![t_a, t_b, t_c, t_d]).err().unwrap();
+    assert_eq!(err, ExchangeError::Cycle(vec!["b".into(), "c".into(), "d".into()]));
+    assert_eq!(err.to_string(), "Cycle detected in translation graph: `b` <-> `c` <-> `d` <-> `b`");
+  }
+
+  #[test]
+  fn test_operation_captures_configuration() {
+    let scale = |block_id: &str, factor: i64| {
+      let (forward_id, reverse_id) = (block_id.to_string(), block_id.to_string());
+      Operation::new(
+        Some(format!("*{}", factor)),
+        move |from: &Model, to: &mut Model| {
+          let v = from.block_by_id(&forward_id).unwrap().get("v").unwrap().as_i64().unwrap() * factor;
+          to.block_by_id_mut(&forward_id).unwrap().insert("v".into(), v.into());
+          Ok(())
+        },
+        move |from: &Model, to: &mut Model| {
+          let v = from.block_by_id(&reverse_id).unwrap().get("v").unwrap().as_i64().unwrap() / factor;
+          to.block_by_id_mut(&reverse_id).unwrap().insert("v".into(), v.into());
+          Ok(())
+        },
+      )
+    };
+    let operation = scale("i", 3);
+    let mut a = Model::new();
+    a.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(2.into()))]));
+    let mut b = a.clone();
+    (operation.forward)(&a, &mut b).unwrap();
+    assert_eq!(b.block_by_id("i").unwrap().get("v").unwrap(), &Value::Number(6.into()));
+    (operation.reverse)(&b, &mut a).unwrap();
+    assert_eq!(a.block_by_id("i").unwrap().get("v").unwrap(), &Value::Number(2.into()));
+    assert_eq!(format!("{:?}", operation.clone()), "Operation { name: Some(\"*3\"), .. }");
+  }
+
+  #[test]
+  fn test_field_mapping() {
+    let mut a = Model::new();
+    a.blocks.insert("x".into(), Block::from_iter([
+      ("name".into(), Value::String("battery_esr".into())),
+      ("value".into(), Value::Number(2.into())),
+    ]));
+    a.root.insert("position".into(), serde_json::json!({"eci": [1.5, 2.5, 3.5]}));
+    let mut b = Model::new();
+    b.blocks.insert("y".into(), Block::from_iter([("esr".into(), Value::Number(0.into()))]));
+
+    let esr = FieldMapping::new(
+      FieldRef::new(BlockSelector::Name("battery_esr".into()), "value"),
+      FieldRef::new(BlockSelector::Id("y".into()), "esr"),
+    ).named("esr").scale(1000.0).offset(5.0).build().unwrap();
+    assert_eq!(esr.name, Some("esr".into()));
+    (esr.forward)(&a, &mut b).unwrap();
+    assert_eq!(b.block_by_id("y").unwrap().get("esr").unwrap(), &Value::Number(2005.into()));
+    b.block_by_id_mut("y").unwrap().insert("esr".into(), Value::Number(4005.into()));
+    (esr.reverse)(&b, &mut a).unwrap();
+    assert_eq!(a.get_block_by_name("battery_esr").unwrap().get("value").unwrap(), &Value::Number(4.into()));
+
+    // Nested paths are created in the target and values are copied as-is without a scale or offset
+    let position = FieldMapping::new(
+      FieldRef::new(BlockSelector::Root, "position.eci.1"),
+      FieldRef::new(BlockSelector::Id("y".into()), "state.y"),
+    ).build().unwrap();
+    (position.forward)(&a, &mut b).unwrap();
+    assert_eq!(b.block_by_id("y").unwrap().get("state").unwrap(), &serde_json::json!({"y": 2.5}));
+
+    let missing = FieldMapping::new(
+      FieldRef::new(BlockSelector::Root, "position.eci.3"),
+      FieldRef::new(BlockSelector::Id("y".into()), "esr"),
+    ).build().unwrap();
+    assert_eq!(
+      (missing.forward)(&a, &mut b).err(),
+      Some(TranslationError::MissingField { block: "root".into(), field: "position.eci.3".into() }),
+    );
+    let mismatch = FieldMapping::new(
+      FieldRef::new(BlockSelector::Name("battery_esr".into()), "name"),
+      FieldRef::new(BlockSelector::Id("y".into()), "esr"),
+    ).scale(2.0).build().unwrap();
+    assert!(matches!((mismatch.forward)(&a, &mut b), Err(TranslationError::TypeMismatch { .. })));
+    assert_eq!(FieldRef::new(BlockSelector::Root, "position.eci.2").get_f64(&a), Ok(3.5));
+    assert_eq!(
+      FieldRef::new(BlockSelector::Name("battery_esr".into()), "name").get_f64(&a),
+      Err(TranslationError::TypeMismatch { block: "name='battery_esr'".into(), field: "name".into(), expected: "a number".into() }),
+    );
+    assert!(FieldMapping::new(
+      FieldRef::new(BlockSelector::Root, "a"),
+      FieldRef::new(BlockSelector::Root, "b"),
+    ).scale(0.0).build().is_err());
+
+    // Values are converted between the units given to the mapping or declared by each Model
+    a.root.insert("mass".into(), Value::from(2500));
+    let mass = FieldMapping::new(
+      FieldRef::new(BlockSelector::Root, "mass"),
+      FieldRef::new(BlockSelector::Id("y".into()), "mass"),
+    ).units("g", "kg").build().unwrap();
+    (mass.forward)(&a, &mut b).unwrap();
+    assert_eq!(b.block_by_id("y").unwrap().get("mass").unwrap(), &Value::from(2.5));
+    a.root.insert("angle".into(), Value::from(std::f64::consts::PI));
+    let angle = FieldMapping::new(
+      FieldRef::new(BlockSelector::Root, "angle"),
+      FieldRef::new(BlockSelector::Id("y".into()), "angle"),
+    ).units("rad", "deg").build().unwrap();
+    (angle.forward)(&a, &mut b).unwrap();
+    assert_eq!(b.block_by_id("y").unwrap().get("angle").unwrap(), &Value::from(180.0));
+    assert_eq!(
+      FieldMapping::new(FieldRef::new(BlockSelector::Root, "a"), FieldRef::new(BlockSelector::Root, "b")).units("g", "m").build().err(),
+      Some(TranslationError::UnitError("Cannot convert from Mass [g] to Length [m]".into())),
+    );
+  }
+
+  #[test]
+  fn test_translation_graph_cycle_detection() {
+    let mut graph = TranslationGraph::new();
+    graph.add_edge("a", "b");
+    graph.add_edge("b", "a");
+    assert_eq!(graph.find_cycle(), None);
+    graph.add_edge("b", "c");
+    graph.add_edge("b", "e");
+    graph.add_edge("c", "d");
+    assert_eq!(graph.find_cycle(), None);
+    graph.add_edge("x", "y");
+    assert_eq!(graph.find_cycle(), None);
+    graph.add_edge("y", "z");
+    graph.add_edge("z", "x");
+    assert_eq!(graph.find_cycle(), Some(vec!["x".to_string(), "y".to_string(), "z".to_string()]));
+  }
+
+  fn conflicting_translation(dir: &tempfile::TempDir, resolutions: &Arc<Mutex<Vec<(String, ConflictResolutions)>>>) -> Translation {
+    let a = ConflictingNode::new("a", write_fixture(dir, "a.json", 0), resolutions.clone());
+    let b = ConflictingNode::new("b", write_fixture(dir, "b.json", 0), resolutions.clone());
+    Translation { from: a, to: b, operations: vec![] }
+  }
+
+  #[test]
+  fn test_exchange_conflict_policy_per_node() {
+    let dir = tempfile::tempdir().unwrap();
+    let resolutions = Arc::new(Mutex::new(vec![]));
+    let options = ExchangeOptions {
+      conflict_policy: ConflictPolicy::PerNode {
+        overrides: HashMap::from([("b".to_string(), ConflictPolicy::UpdateRep)]),
+        default: Box::new(ConflictPolicy::KeepRep),
+      },
+      ..Default::default()
+    };
+    Exchange::with_options(vec![conflicting_translation(&dir, &resolutions)], options).unwrap();
+    assert_eq!(*resolutions.lock().unwrap(), vec![
+      ("a".to_string(), ConflictResolutions::KeepRep),
+      ("b".to_string(), ConflictResolutions::UpdateRep),
+    ]);
+  }
+
+  #[test]
+  fn test_exchange_conflict_policy_callback() {
+    let dir = tempfile::tempdir().unwrap();
+    let resolutions = Arc::new(Mutex::new(vec![]));
+    let options = ExchangeOptions {
+      conflict_policy: ConflictPolicy::Custom(Arc::new(|iden, diff| {
+        let v = &diff.updated_blocks.get("i").unwrap().updated_fields.get("v").unwrap().new_value;
+        assert_eq!(v, &Value::Number((-1).into()));
+        match iden {
+          "a" => Some(ConflictResolutions::UpdateRep),
+          _ => None,
+        }
+      })),
+      ..Default::default()
+    };
+    let result = Exchange::with_options(vec![conflicting_translation(&dir, &resolutions)], options);
+    assert_eq!(result.err(), Some(ExchangeError::UnresolvedConflict("b".into())));
+    assert_eq!(*resolutions.lock().unwrap(), vec![("a".to_string(), ConflictResolutions::UpdateRep)]);
+  }
+
+  #[test]
+  fn test_exchange_conflict_policy_fail_fast() {
+    let dir = tempfile::tempdir().unwrap();
+    let resolutions = Arc::new(Mutex::new(vec![]));
+    let options = ExchangeOptions { conflict_policy: ConflictPolicy::FailFast, ..Default::default() };
+    let result = Exchange::with_options(vec![conflicting_translation(&dir, &resolutions)], options);
+    assert_eq!(result.err(), Some(ExchangeError::UnresolvedConflict("a".into())));
+    assert!(resolutions.lock().unwrap().is_empty());
+  }
+
+  /// Translation `a -> b` copying `v` whose forward Operation also writes `v = 42` to `b`'s file, simulating an edit made
+  /// while the round is in progress
+  fn concurrently_edited_translation(dir: &tempfile::TempDir) -> Translation {
+    let a_filename = write_fixture(dir, "a.json", 1);
+    let b_filename = write_fixture(dir, "b.json", 0);
+    let mut a_model = read_model(&a_filename).unwrap();
+    a_model.blocks.get_mut("i").unwrap().insert("edit".into(), Value::String(b_filename.clone()));
+    write_model(&a_filename, &a_model).unwrap();
+    let copy = Operation::new(
+      None,
+      |from: &Model, to: &mut Model| {
+        let edited = from.blocks.get("i").unwrap().get("edit").unwrap().as_str().unwrap();
+        let mut model = Model::new();
+        model.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(42.into()))]));
+        write_model(edited, &model).unwrap();
+        to.blocks.get_mut("i").unwrap().insert("v".into(), from.blocks.get("i").unwrap().get("v").unwrap().clone());
+        Ok(())
+      },
+      |from: &Model, to: &mut Model| {
+        to.blocks.get_mut("i").unwrap().insert("v".into(), from.blocks.get("i").unwrap().get("v").unwrap().clone());
+        Ok(())
+      },
+    );
+    let a = SedaroML::new("a".into(), a_filename);
+    let b = SedaroML::new("b".into(), b_filename);
+    Translation { from: a, to: b, operations: vec![copy] }
+  }
+
+  fn read_v(filename: &str) -> i64 {
+    read_model(filename).unwrap().blocks.get("i").unwrap().get("v").unwrap().as_i64().unwrap()
+  }
+
+  #[test]
+  fn test_exchange_concurrent_edit_keep_rep() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { conflict_policy: ConflictPolicy::KeepRep, ..Default::default() };
+    let exchange = Exchange::with_options(vec![concurrently_edited_translation(&dir)], options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].node, "b");
+    assert_eq!(report.conflicts[0].resolution, ConflictResolutions::KeepRep);
+    let v = &report.conflicts[0].diff.updated_blocks.get("i").unwrap().updated_fields.get("v").unwrap();
+    assert_eq!((&v.old_value, &v.new_value), (&Value::Number(42.into()), &Value::Number(1.into())));
+    assert_eq!(report.changed_nodes().count(), 0);
+
+    // The edit survives and is propagated back to `a` by a round triggered from `b`
+    let report = exchange.round_lock().wait_for_change("b", report.round).unwrap();
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["a"]);
+    assert_eq!(read_v(dir.path().join("a.json").to_str().unwrap()), 42);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 42);
+  }
+
+  #[test]
+  fn test_exchange_concurrent_edit_update_rep() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { conflict_policy: ConflictPolicy::UpdateRep, ..Default::default() };
+    let exchange = Exchange::with_options(vec![concurrently_edited_translation(&dir)], options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].resolution, ConflictResolutions::UpdateRep);
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["b"]);
+    exchange.wait_for_round().unwrap();
+    assert_eq!(read_v(dir.path().join("a.json").to_str().unwrap()), 1);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 1);
+  }
+
+  #[test]
+  fn test_exchange_concurrent_edit_merge() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { conflict_policy: ConflictPolicy::Merge, ..Default::default() };
+    let exchange = Exchange::with_options(vec![concurrently_edited_translation(&dir)], options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].resolution, ConflictResolutions::Merge);
+    assert_eq!(report.conflicts[0].merge_conflicts, vec![MergeConflict::Field {
+      block: Some("i".into()), field: "v".into(),
+      base: Some(Box::new(0.into())), ours: Some(Box::new(42.into())), theirs: Some(Box::new(1.into())),
+    }]);
+    assert_eq!(report.changed_nodes().count(), 0);
+
+    // Both changed `v`, so the merge keeps the edit on disk and propagates it back to `a`
+    let report = exchange.round_lock().wait_for_change("b", report.round).unwrap();
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["a"]);
+    assert_eq!(read_v(dir.path().join("a.json").to_str().unwrap()), 42);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 42);
+  }
+
+  #[test]
+  fn test_exchange_concurrent_edit_during_resolution() {
+    let dir = tempfile::tempdir().unwrap();
+    let b_filename = dir.path().join("b.json").to_str().unwrap().to_string();
+    let calls = Arc::new(Mutex::new(0));
+    let calls_clone = calls.clone();
+    let options = ExchangeOptions {
+      conflict_policy: ConflictPolicy::Custom(Arc::new(move |_, diff| {
+        let mut calls = calls_clone.lock().unwrap();
+        *calls += 1;
+        if *calls == 1 {
+          // The file is saved again while the first conflict is being resolved, e.g. at the prompt
+          let mut model = Model::new();
+          model.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(7.into()))]));
+          write_model(&b_filename, &model).unwrap();
+          Some(ConflictResolutions::UpdateRep)
+        } else {
+          let v = &diff.updated_blocks.get("i").unwrap().updated_fields.get("v").unwrap().old_value;
+          assert_eq!(v, &Value::Number(7.into()));
+          Some(ConflictResolutions::KeepRep)
+        }
+      })),
+      ..Default::default()
+    };
+    let exchange = Exchange::with_options(vec![concurrently_edited_translation(&dir)], options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    // The second save isn't overwritten by the first resolution, but resolved again
+    assert_eq!(*calls.lock().unwrap(), 2);
+    assert_eq!(report.conflicts.len(), 2);
+    assert_eq!(report.changed_nodes().count(), 0);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 7);
+  }
+
+  #[test]
+  fn test_exchange_halted_on_panic() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = SedaroML::new("a".into(), write_fixture(&dir, "a.json", 1));
+    let b = SedaroML::new("b".into(), write_fixture(&dir, "b.json", 0));
+    let panicking = Operation::new(None, |_: &Model, _: &mut Model| panic!("Operation panicked"), |_: &Model, _: &mut Model| Ok(()));
+    let exchange = Exchange::new(vec![Translation { from: a, to: b, operations: vec![panicking] }]).unwrap();
+    assert_eq!(exchange.trigger_and_wait("a").err(), Some(ExchangeError::Halted));
+    assert_eq!(exchange.wait_for_round().err(), Some(ExchangeError::Halted));
+    assert!(exchange.round_lock().is_halted());
+  }
+
+  #[test]
+  fn test_exchange_trigger_during_round() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { approval: ApprovalMode::Manual, ..Default::default() };
+    let exchange = Exchange::with_options(vec![copy_translation(&dir, 1, 0)], options).unwrap();
+    exchange.trigger_watch_for_model("a".into()).unwrap();
+    exchange.wait_for_changeset();
+
+    // Triggered while round 1 from the same Node is in progress, so it must wait for round 2
+    thread::scope(|scope| {
+      let waiter = scope.spawn(|| exchange.trigger_and_wait("a"));
+      thread::sleep(Duration::from_millis(50));
+      assert_eq!(exchange.approve().unwrap().round, 1);
+      assert_eq!(waiter.join().unwrap().unwrap().round, 2);
+    });
+  }
+
+  #[test]
+  fn test_exchange_dry_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let exchange = Exchange::new(vec![copy_translation(&dir, 1, 0)]).unwrap();
+    let mut model = Model::new();
+    model.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(5.into()))]));
+    let diffs = exchange.dry_run("a", model).unwrap();
+    assert_eq!(diffs.keys().collect::<Vec<_>>(), vec!["b"]);
+    let v = diffs.get("b").unwrap().updated_blocks.get("i").unwrap().updated_fields.get("v").unwrap();
+    assert_eq!((&v.old_value, &v.new_value), (&Value::Number(0.into()), &Value::Number(5.into())));
+
+    // Nothing was written, notified, or counted as a round
+    assert_eq!(read_v(dir.path().join("a.json").to_str().unwrap()), 1);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 0);
+    let b = exchange.nodes.lock().unwrap().get("b").unwrap().clone();
+    assert_eq!(b.lock().unwrap().rep().blocks.get("i").unwrap().get("v").unwrap(), &Value::Number(0.into()));
+    assert_eq!(exchange.round_lock().completed(), 0);
+    assert_eq!(exchange.dry_run("z", Model::new()).err(), Some(ExchangeError::UnknownNode("z".into())));
+  }
+
+  #[test]
+  fn test_exchange_diff_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut model = Model::new();
+    model.blocks.insert("i".into(), Block::from_iter([("v".into(), serde_json::json!(1.0))]));
+
+    let exchange = Exchange::new(vec![copy_translation(&dir, 1, 1)]).unwrap();
+    assert_eq!(exchange.dry_run("a", model.clone()).unwrap().keys().collect::<Vec<_>>(), vec!["b"]);
+    exchange.shutdown().unwrap();
+
+    let diff_options = DiffOptions { numeric_equality: true, ..Default::default() };
+    let options = ExchangeOptions { diff_options, ..Default::default() };
+    let exchange = Exchange::with_options(vec![copy_translation(&dir, 1, 1)], options).unwrap();
+    assert!(exchange.dry_run("a", model.clone()).unwrap().is_empty());
+
+    // Once something else changes, the Node is sent every difference so that it matches the representation on disk
+    model.blocks.get_mut("i").unwrap().insert("w".into(), serde_json::json!(2));
+    let diffs = exchange.dry_run("a", model).unwrap();
+    let i = &diffs["b"].updated_blocks["i"];
+    assert_eq!(i.updated_fields["v"].new_value, serde_json::json!(1.0));
+    assert_eq!(i.added_fields["w"], serde_json::json!(2));
+  }
+
+  #[test]
+  fn test_exchange_approval_approve() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { approval: ApprovalMode::Manual, ..Default::default() };
+    let exchange = Exchange::with_options(vec![copy_translation(&dir, 1, 0)], options).unwrap();
+    assert_eq!(exchange.approve().err(), Some(ExchangeError::NoPendingChangeset));
+
+    exchange.trigger_watch_for_model("a".into()).unwrap();
+    let changeset = exchange.wait_for_changeset();
+    assert_eq!((changeset.round, changeset.trigger.as_str()), (1, "a"));
+    assert_eq!(changeset.diffs.keys().collect::<Vec<_>>(), vec!["b"]);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 0);
+
+    let report = exchange.approve().unwrap();
+    assert_eq!(report.approved, Some(true));
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["b"]);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 1);
+    assert!(exchange.pending_changeset().is_none());
+  }
+
+  #[test]
+  fn test_exchange_approval_reject() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { approval: ApprovalMode::Manual, ..Default::default() };
+    let exchange = Exchange::with_options(vec![copy_translation(&dir, 1, 0)], options).unwrap();
+
+    exchange.trigger_watch_for_model("a".into()).unwrap();
+    exchange.wait_for_changeset();
+    let report = exchange.reject().unwrap();
+    assert_eq!(report.approved, Some(false));
+    assert_eq!(report.changed_nodes().count(), 0);
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 0);
+
+    // A changeset still pending on shutdown is rejected
+    exchange.trigger_watch_for_model("a".into()).unwrap();
+    exchange.wait_for_changeset();
+    let round_lock = exchange.round_lock().clone();
+    assert_eq!(exchange.shutdown(), Ok(()));
+    assert_eq!(round_lock.last_report().unwrap().approved, Some(false));
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 0);
+  }
+
+  /// Translations `a -> b -> c` copying block `i`, where `b -> c` first runs an Operation that fails after modifying `c`
+  fn failing_translations(dir: &tempfile::TempDir) -> Vec<Translation> {
+    let t_a = copy_translation(dir, 1, 0);
+    let c = SedaroML::new("c".into(), write_fixture(dir, "c.json", 0));
+    let fail = Operation::new(
+      Some("fail".into()),
+      |_: &Model, to: &mut Model| {
+        to.blocks.insert("j".into(), Block::new());
+        to.block_by_id("missing")?;
+        Ok(())
+      },
+      |_: &Model, _: &mut Model| Err(TranslationError::Custom("Not invertible".into())),
+    );
+    let t_b = Translation { from: t_a.to.clone(), to: c, operations: vec![fail, copy_operation()] };
+    vec![t_a, t_b]
+  }
+
+  #[test]
+  fn test_exchange_failure_abort_round() {
+    let dir = tempfile::tempdir().unwrap();
+    let exchange = Exchange::new(failing_translations(&dir)).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert!(report.aborted);
+    assert_eq!(report.changed_nodes().count(), 0);
+    assert_eq!(
+      report.translations[1].operations[0].outcome,
+      OperationOutcome::Failed(TranslationError::BlockNotFound("Block ID not found: missing".into())),
+    );
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 0);
+    assert_eq!(read_v(dir.path().join("c.json").to_str().unwrap()), 0);
+
+    let mut model = Model::new();
+    model.blocks.insert("i".into(), Block::from_iter([("v".into(), Value::Number(5.into()))]));
+    let err = exchange.dry_run("a", model).err().unwrap();
+    assert_eq!(err.to_string(), "Translation `b` -> `c` failed: Block not found: Block ID not found: missing");
+  }
+
+  #[test]
+  fn test_exchange_failure_skip_operation() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { failure_policy: FailurePolicy::SkipOperation, ..Default::default() };
+    let exchange = Exchange::with_options(failing_translations(&dir), options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert!(!report.aborted);
+    assert_eq!(report.changed_nodes().collect::<Vec<_>>(), vec!["b", "c"]);
+    let outcomes = report.translations[1].operations.iter().map(|o| &o.outcome).collect::<Vec<_>>();
+    assert!(matches!(outcomes[..], [OperationOutcome::Failed(_), OperationOutcome::Changed]));
+    // Changes made by the failed Operation are discarded
+    let c = read_model(dir.path().join("c.json").to_str().unwrap()).unwrap();
+    assert_eq!(c.blocks.keys().collect::<Vec<_>>(), vec!["i"]);
+    assert_eq!(read_v(dir.path().join("c.json").to_str().unwrap()), 1);
+  }
+
+  #[test]
+  fn test_exchange_failure_halt() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExchangeOptions { failure_policy: FailurePolicy::Halt, ..Default::default() };
+    let exchange = Exchange::with_options(failing_translations(&dir), options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert!(report.aborted);
+    // The translation thread exits instead of taking the process down, so `wait` returns
+    exchange.wait();
+    assert_eq!(read_v(dir.path().join("b.json").to_str().unwrap()), 0);
+  }
+
+  #[test]
+  fn test_exchange_failure_file() {
+    let dir = tempfile::tempdir().unwrap();
+    // `b`'s file becomes unreadable while the round is in progress
+    let b_filename = write_fixture(&dir, "b.json", 0);
+    let corrupt = Operation::new(
+      None,
+      move |from: &Model, to: &mut Model| {
+        std::fs::write(&b_filename, "{").unwrap();
+        to.blocks.insert("i".into(), from.block_by_id("i")?.clone());
+        Ok(())
+      },
+      |_: &Model, _: &mut Model| Err(TranslationError::Custom("Not invertible".into())),
+    );
+    let a = SedaroML::new("a".into(), write_fixture(&dir, "a.json", 1));
+    let b = SedaroML::new("b".into(), dir.path().join("b.json").to_str().unwrap().to_string());
+    let translation = Translation { from: a, to: b, operations: vec![corrupt] };
+    let options = ExchangeOptions { failure_policy: FailurePolicy::Halt, ..Default::default() };
+    let exchange = Exchange::with_options(vec![translation], options).unwrap();
+    let report = exchange.trigger_and_wait("a").unwrap();
+    assert!(report.aborted);
+    assert_eq!(report.changed_nodes().count(), 0);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].starts_with("Failed to access SedaroML of node `b`"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("b.json")).unwrap(), "{");
+    // The round is aborted rather than panicking with the fingerprints locked, and the thread halts
+    assert_eq!(exchange.trigger_and_wait("a").err(), Some(ExchangeError::Halted));
+    assert!(exchange.round_lock().is_halted());
   }
-}
\ No newline at end of file
+}
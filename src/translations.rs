@@ -1,19 +1,91 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::{Arc, Mutex};
-use crate::model::sedaroml::Model;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::model::sedaroml::{Block, Model, ModelError};
+use crate::model::units::{Unit, UnitError};
 use crate::nodes::traits::Exchangeable;
 
-#[derive(Debug)]
-pub enum TranslationError {}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TranslationError {
+  /// No block with the given ID (or matching the given filter) exists
+  BlockNotFound(String),
+  /// No block of the given type exists
+  BlockTypeNotFound(String),
+  /// A block doesn't have a field that the Operation reads
+  MissingField { block: String, field: String },
+  /// A field doesn't hold a value of the type the Operation expects
+  TypeMismatch { block: String, field: String, expected: String },
+  /// A quantity couldn't be converted between units
+  UnitError(String),
+  Custom(String),
+}
 
-type ModelOperationFn = fn(&Model, &mut Model) -> Result<(), TranslationError>;
+impl fmt::Display for TranslationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TranslationError::BlockNotFound(msg) => write!(f, "Block not found: {}", msg),
+      TranslationError::BlockTypeNotFound(msg) => write!(f, "Block type not found: {}", msg),
+      TranslationError::MissingField { block, field } => write!(f, "Block `{}` has no field `{}`", block, field),
+      TranslationError::TypeMismatch { block, field, expected } => write!(f, "Field `{}` of block `{}` is not {}", field, block, expected),
+      TranslationError::UnitError(msg) => write!(f, "Unit error: {}", msg),
+      TranslationError::Custom(msg) => write!(f, "{}", msg),
+    }
+  }
+}
 
-#[derive(Debug, Clone)]
+impl std::error::Error for TranslationError {}
+
+impl From<ModelError> for TranslationError {
+  fn from(e: ModelError) -> Self {
+    match e {
+      ModelError::BlockNotFound(msg) => TranslationError::BlockNotFound(msg),
+      ModelError::BlockTypeNotFound(msg) => TranslationError::BlockTypeNotFound(msg),
+      ModelError::FileError(msg) => TranslationError::Custom(msg),
+      ModelError::FieldNotFound { block, field } => TranslationError::MissingField { block, field },
+      ModelError::NotANumber { block, field } => TranslationError::TypeMismatch { block, field, expected: "a number".into() },
+      ModelError::NotARelationship { block, field } => TranslationError::TypeMismatch { block, field, expected: "a relationship".into() },
+      ModelError::DuplicateBlock(id) => TranslationError::Custom(format!("Block ID already exists: {}", id)),
+      ModelError::InvalidBlock(msg) => TranslationError::Custom(msg),
+      ModelError::UnitError(e) => e.into(),
+    }
+  }
+}
+
+impl From<UnitError> for TranslationError {
+  fn from(e: UnitError) -> Self {
+    TranslationError::UnitError(e.to_string())
+  }
+}
+
+/// Function translating one Model into another.  Closures can capture configuration such as block IDs, filters or unit
+/// factors.
+pub type ModelOperationFn = Arc<dyn Fn(&Model, &mut Model) -> Result<(), TranslationError> + Send + Sync>;
+
+#[derive(Clone)]
 pub struct Operation {
   pub name: Option<String>,
   pub forward: ModelOperationFn,
   pub reverse: ModelOperationFn,
 }
 
+impl Operation {
+  pub fn new<F, R>(name: Option<String>, forward: F, reverse: R) -> Operation
+  where
+    F: Fn(&Model, &mut Model) -> Result<(), TranslationError> + Send + Sync + 'static,
+    R: Fn(&Model, &mut Model) -> Result<(), TranslationError> + Send + Sync + 'static,
+  {
+    Operation { name, forward: Arc::new(forward), reverse: Arc::new(reverse) }
+  }
+}
+
+impl fmt::Debug for Operation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Operation").field("name", &self.name).finish_non_exhaustive()
+  }
+}
+
 pub enum OperationFunction {
   Forward(Option<String>, ModelOperationFn),
   Reverse(Option<String>, ModelOperationFn),
@@ -23,4 +95,224 @@ pub struct Translation {
   pub from: Arc<Mutex<dyn Exchangeable + Sync + Send>>,
   pub to: Arc<Mutex<dyn Exchangeable + Sync + Send>>,
   pub operations: Vec<Operation>,
-}
\ No newline at end of file
+}
+/// Selects a block of a Model, or its root
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockSelector {
+  /// The block with the given ID
+  Id(String),
+  /// The first block with the given `name`
+  Name(String),
+  /// The first block whose fields hold all of the given values
+  Where(HashMap<String, Value>),
+  /// The root of the Model
+  Root,
+}
+
+impl BlockSelector {
+  pub fn select<'a>(&self, model: &'a Model) -> Result<&'a Block, TranslationError> {
+    Ok(match self {
+      BlockSelector::Id(id) => model.block_by_id(id)?,
+      BlockSelector::Name(name) => model.get_block_by_name(name)?,
+      BlockSelector::Where(filter) => model.get_first_block_where(filter)?,
+      BlockSelector::Root => &model.root,
+    })
+  }
+  pub fn select_mut<'a>(&self, model: &'a mut Model) -> Result<&'a mut Block, TranslationError> {
+    Ok(match self {
+      BlockSelector::Id(id) => model.block_by_id_mut(id)?,
+      BlockSelector::Name(name) => model.get_block_by_name_mut(name)?,
+      BlockSelector::Where(filter) => model.get_first_block_where_mut(filter)?,
+      BlockSelector::Root => &mut model.root,
+    })
+  }
+}
+
+impl fmt::Display for BlockSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      BlockSelector::Id(id) => write!(f, "{}", id),
+      BlockSelector::Name(name) => write!(f, "name='{}'", name),
+      BlockSelector::Where(filter) => {
+        let mut conditions = filter.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+        conditions.sort();
+        write!(f, "{}", conditions.join(", "))
+      },
+      BlockSelector::Root => write!(f, "root"),
+    }
+  }
+}
+
+/// A field of a Model: a block and a `.`-separated path of keys and array indices within it (e.g. `position.0`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldRef {
+  pub selector: BlockSelector,
+  pub path: String,
+}
+
+impl FieldRef {
+  pub fn new(selector: BlockSelector, path: &str) -> FieldRef {
+    FieldRef { selector, path: path.to_string() }
+  }
+
+  pub fn get<'a>(&self, model: &'a Model) -> Result<&'a Value, TranslationError> {
+    let block = self.selector.select(model)?;
+    let mut segments = self.path.split('.');
+    let mut value = block.get(segments.next().unwrap()).ok_or_else(|| self.missing())?;
+    for segment in segments {
+      value = match value {
+        Value::Array(array) => segment.parse::<usize>().ok().and_then(|i| array.get(i)),
+        Value::Object(object) => object.get(segment),
+        _ => None,
+      }.ok_or_else(|| self.missing())?;
+    }
+    Ok(value)
+  }
+
+  /// The field as a number
+  pub fn get_f64(&self, model: &Model) -> Result<f64, TranslationError> {
+    self.get(model)?.as_f64().ok_or_else(|| self.mismatch("a number"))
+  }
+
+  /// Sets the field, creating it (and any missing object keys along the path) if needed
+  pub fn set(&self, model: &mut Model, new_value: Value) -> Result<(), TranslationError> {
+    let missing = self.missing();
+    let block = self.selector.select_mut(model)?;
+    let segments = self.path.split('.').collect::<Vec<_>>();
+    let (last, parents) = segments.split_last().unwrap();
+    if parents.is_empty() {
+      block.insert(last.to_string(), new_value);
+      return Ok(());
+    }
+    let mut value = block.entry(parents[0].to_string()).or_insert_with(|| Value::Object(Default::default()));
+    for segment in parents[1..].iter().chain([last]) {
+      value = match value {
+        Value::Array(array) => segment.parse::<usize>().ok().and_then(|i| array.get_mut(i)),
+        Value::Object(object) => Some(object.entry(segment.to_string()).or_insert(Value::Null)),
+        _ => None,
+      }.ok_or_else(|| missing.clone())?;
+    }
+    *value = new_value;
+    Ok(())
+  }
+
+  /// The unit declared for the field in the Model's `_quantityKinds`.  Only top-level fields can declare units.
+  pub fn declared_unit(&self, model: &Model) -> Result<Option<Unit>, TranslationError> {
+    if self.path.contains('.') {
+      return Ok(None);
+    }
+    Ok(model.declared_unit(self.selector.select(model)?, &self.path)?)
+  }
+
+  fn missing(&self) -> TranslationError {
+    TranslationError::MissingField { block: self.selector.to_string(), field: self.path.clone() }
+  }
+
+  fn mismatch(&self, expected: &str) -> TranslationError {
+    TranslationError::TypeMismatch { block: self.selector.to_string(), field: self.path.clone(), expected: expected.to_string() }
+  }
+}
+
+/// Declarative, bidirectional mapping from one field to another.
+///
+/// The forward Operation sets `target = source * scale + offset` and the reverse Operation sets
+/// `source = (target - offset) / scale`.  Where the units of both fields are known, either from `units` or from the
+/// quantity kinds declared by each Model, the value is converted to the target's unit before it is scaled.  Without a
+/// scale, offset or unit conversion the value is copied as-is and need not be a number.
+#[derive(Debug, Clone)]
+pub struct FieldMapping {
+  name: Option<String>,
+  source: FieldRef,
+  target: FieldRef,
+  scale: f64,
+  offset: f64,
+  units: Option<(String, String)>,
+}
+
+impl FieldMapping {
+  pub fn new(source: FieldRef, target: FieldRef) -> FieldMapping {
+    FieldMapping { name: None, source, target, scale: 1.0, offset: 0.0, units: None }
+  }
+  pub fn named(mut self, name: &str) -> FieldMapping {
+    self.name = Some(name.to_string());
+    self
+  }
+  pub fn scale(mut self, scale: f64) -> FieldMapping {
+    self.scale = scale;
+    self
+  }
+  pub fn offset(mut self, offset: f64) -> FieldMapping {
+    self.offset = offset;
+    self
+  }
+  /// Sets the units of the source and target fields (e.g. `"g"` and `"kg"`), overriding any declared by the Models
+  pub fn units(mut self, source: &str, target: &str) -> FieldMapping {
+    self.units = Some((source.to_string(), target.to_string()));
+    self
+  }
+
+  /// Builds the forward and reverse Operations for the mapping
+  pub fn build(self) -> Result<Operation, TranslationError> {
+    if self.scale == 0.0 || !self.scale.is_finite() || !self.offset.is_finite() {
+      return Err(TranslationError::Custom(format!("Mapping {} -> {} is not invertible", self.source.path, self.target.path)));
+    }
+    let (source_unit, target_unit) = match &self.units {
+      Some((source, target)) => {
+        let (source, target) = (Unit::parse(source)?, Unit::parse(target)?);
+        source.convert(0.0, &target)?;
+        (Some(source), Some(target))
+      },
+      None => (None, None),
+    };
+    let (scale, offset) = (self.scale, self.offset);
+    let (source, target) = (self.source.clone(), self.target.clone());
+    let forward = move |from: &Model, to: &mut Model| {
+      let units = (unit_or_declared(source_unit, &source, from)?, unit_or_declared(target_unit, &target, to)?);
+      let value = transform(&source, source.get(from)?, scale, offset, units, |v| Ok(convert(v, units)? * scale + offset))?;
+      target.set(to, value)
+    };
+    let (source, target) = (self.source, self.target);
+    let reverse = move |from: &Model, to: &mut Model| {
+      let units = (unit_or_declared(target_unit, &target, from)?, unit_or_declared(source_unit, &source, to)?);
+      let value = transform(&target, target.get(from)?, scale, offset, units, |v| convert((v - offset) / scale, units))?;
+      source.set(to, value)
+    };
+    Ok(Operation::new(self.name, forward, reverse))
+  }
+}
+
+fn unit_or_declared(unit: Option<Unit>, field: &FieldRef, model: &Model) -> Result<Option<Unit>, TranslationError> {
+  match unit {
+    Some(unit) => Ok(Some(unit)),
+    None => field.declared_unit(model),
+  }
+}
+
+/// Converts a value between units where both are known
+fn convert(value: f64, units: (Option<Unit>, Option<Unit>)) -> Result<f64, TranslationError> {
+  match units {
+    (Some(from), Some(to)) => Ok(from.convert(value, &to)?),
+    _ => Ok(value),
+  }
+}
+
+/// Applies `f` to a numeric field value, keeping integers as integers where the result is whole.  Values are copied
+/// as-is when the mapping has no scale, offset or unit conversion.
+fn transform(
+  field: &FieldRef, value: &Value, scale: f64, offset: f64, units: (Option<Unit>, Option<Unit>),
+  f: impl Fn(f64) -> Result<f64, TranslationError>,
+) -> Result<Value, TranslationError> {
+  let converts = matches!(units, (Some(from), Some(to)) if from != to);
+  if scale == 1.0 && offset == 0.0 && !converts {
+    return Ok(value.clone());
+  }
+  let Some(v) = value.as_f64() else {
+    return Err(field.mismatch("a number"));
+  };
+  let result = f(v)?;
+  if value.is_i64() && result.fract() == 0.0 && result.abs() < i64::MAX as f64 {
+    Ok(Value::from(result as i64))
+  } else {
+    Ok(Value::from(result))
+  }
+}
@@ -1,21 +1,19 @@
-use std::fs::File;
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Read;
 use std::io::Write;
 use serde_json::Value;
 use serde_json;
 use crate::model::sedaroml::ModelError;
-use std::process;
-use pyo3::prelude::*;
-use pyo3::Python;
-use log::info;
 
 pub fn read_json(file_path: &str) -> Result<Value, ModelError> {
   match File::open(file_path) {
     Ok(mut file) => {
       let mut contents = String::new();
-      file.read_to_string(&mut contents).expect(format!("Cannot read file {}", file_path).as_str());
-      let v = serde_json::from_str(&contents).unwrap();
-      Ok(v)
+      if file.read_to_string(&mut contents).is_err() {
+        return Err(ModelError::FileError(format!("Cannot read file {file_path}")));
+      }
+      serde_json::from_str(&contents).map_err(|e| ModelError::FileError(format!("Invalid JSON in file {file_path}: {e}")))
     },
     Err(_) => return Err(ModelError::FileError(format!("Cannot read file {file_path}"))),
   }
@@ -33,13 +31,13 @@ pub fn write_json(file_path: &str, json_str: &str) -> Result<(), ModelError> {
   }
 }
 
-// This is a really annoying hack to allow for ctrl+c to terminate the exchange after spawning xlwings from python for excel conversion
-pub fn python_signal_handler() -> PyResult<()> {
-  Python::with_gil(|py| {
-    py.check_signals().unwrap_or_else(|e| {
-      info!("Recieved signal in python: {}.  Force terminating.", e);
-      process::exit(1);
-    });
-    Ok(())
-  })
-}
\ No newline at end of file
+/// Hash of a file's contents, used to tell whether a file has changed since it was last read or written
+pub fn fingerprint(contents: &str) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  contents.hash(&mut hasher);
+  hasher.finish()
+}
+
+pub fn read_fingerprint(file_path: &str) -> Option<u64> {
+  fs::read_to_string(file_path).ok().map(|contents| fingerprint(&contents))
+}